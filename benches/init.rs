@@ -0,0 +1,83 @@
+#![feature(test)]
+
+extern crate lunch;
+extern crate rayon;
+extern crate tempdir;
+extern crate test;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+
+use rayon::ThreadPoolBuilder;
+use tempdir::TempDir;
+use test::Bencher;
+
+use lunch::env::{InitOptions, LunchEnv};
+
+const CORPUS_SIZE: usize = 5000;
+const CORPUS_DIRS: usize = 50;
+
+fn corpus() -> TempDir {
+    let tmp_dir = TempDir::new("lunch-bench").unwrap();
+    for n in 0..CORPUS_SIZE {
+        let dir = tmp_dir
+            .path()
+            .join("applications")
+            .join(format!("vendor{}", n % CORPUS_DIRS));
+        fs::create_dir_all(&dir).unwrap();
+        let mut file = File::create(dir.join(format!("app{}.desktop", n))).unwrap();
+        write!(
+            file,
+            "[Desktop Entry]
+Type=Application
+Name=Application {n}
+Name[de]=Anwendung {n}
+Name[fr]=Application {n}
+GenericName=Generic Tool {n}
+GenericName[de]=Allgemeines Werkzeug {n}
+Comment=Does something useful with item number {n}
+Exec=app{n} %U
+Icon=app{n}
+Categories=Utility;Development;
+Keywords=tool;thing{n};
+Keywords[de]=Werkzeug;Ding{n};
+Actions=new-window;
+
+[Desktop Action new-window]
+Name=New Window
+Exec=app{n} --new-window
+",
+            n = n
+        ).unwrap();
+    }
+    tmp_dir
+}
+
+fn bench_init(b: &mut Bencher, threads: usize) {
+    let corpus = corpus();
+    env::set_var("XDG_DATA_HOME", corpus.path());
+    env::set_var("XDG_DATA_DIRS", corpus.path().join("nonexistent"));
+    env::set_var("LANG", "de_DE.UTF-8");
+    env::remove_var("LC_ALL");
+    env::remove_var("LC_MESSAGES");
+    let options = InitOptions {
+        all_translations: true,
+        no_cache: true,
+    };
+    let pool = ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+    b.iter(|| {
+        let loaded = pool.install(|| LunchEnv::init_with(&options).map(|env| env.lunchables.len()));
+        assert_eq!(loaded.unwrap(), CORPUS_SIZE * 2);
+    });
+}
+
+#[bench]
+fn init_5000_files_single_thread(b: &mut Bencher) {
+    bench_init(b, 1);
+}
+
+#[bench]
+fn init_5000_files_parallel(b: &mut Bencher) {
+    bench_init(b, 0);
+}
@@ -2,6 +2,9 @@
 #![feature(try_from)]
 #![feature(slice_patterns)]
 
+extern crate bincode;
+extern crate caseless;
+extern crate chrono;
 extern crate clap;
 #[macro_use]
 extern crate derive_builder;
@@ -9,8 +12,18 @@ extern crate env_logger;
 #[macro_use]
 extern crate error_chain;
 #[macro_use]
+extern crate lazy_static;
+extern crate libc;
+#[macro_use]
 extern crate log;
 extern crate peeking_take_while;
+extern crate rayon;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate termion;
+extern crate unicode_normalization;
+extern crate url;
 extern crate users;
 extern crate xdg;
 
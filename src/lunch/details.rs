@@ -0,0 +1,19 @@
+use std::path::Path;
+
+pub trait Details {
+    fn id(&self) -> Option<&str> {
+        None
+    }
+
+    fn icon(&self) -> Option<&str> {
+        None
+    }
+
+    fn location(&self) -> Option<&Path> {
+        None
+    }
+
+    fn terminal(&self) -> bool {
+        false
+    }
+}
@@ -1,45 +1,192 @@
-use std::fmt::Display;
+use std::fmt::{self, Display, Formatter};
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use super::errors::*;
 
-use super::Launch;
-use super::Search;
+use super::{Details, Launch};
+use super::{unambiguous_match, Fuzzy, Match, Search};
 use super::keyword::Keyword;
+use super::rank::Rank;
 
 use super::freedesktop::env::init_lunch;
 
-pub trait Lunchable: Launch + Search + Display {}
+pub trait Lunchable: Launch + Search + Details + Display {}
 
 impl<T> Lunchable for T
 where
-    T: Launch + Search + Display,
+    T: Launch + Search + Details + Display,
 {
 }
 
 pub struct LunchEnv {
     pub lunchables: Vec<Rc<Lunchable>>,
+    pub fuzzy: Fuzzy,
+    pub report: LoadReport,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LoadStage {
+    Open,
+    Parse,
+    Build,
+}
+
+impl Display for LoadStage {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let stage = match *self {
+            LoadStage::Open => "open",
+            LoadStage::Parse => "parse",
+            LoadStage::Build => "build",
+        };
+        write!(f, "{}", stage)
+    }
+}
+
+#[derive(Debug)]
+pub struct Skipped {
+    pub path: PathBuf,
+    pub stage: LoadStage,
+    pub error: Error,
+}
+
+impl Display for Skipped {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.path.display(), self.stage, self.error)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LoadReport {
+    pub skipped: Vec<Skipped>,
+    pub loaded_leniently: Vec<Skipped>,
+}
+
+impl LoadReport {
+    pub fn skip(&mut self, path: PathBuf, stage: LoadStage, error: Error) {
+        warn!("Skipping '{}' ({}): {}", path.display(), stage, error);
+        self.skipped.push(Skipped { path, stage, error });
+    }
+
+    pub fn load_leniently(&mut self, path: PathBuf, error: Error) {
+        warn!("Loading '{}' leniently: {}", path.display(), error);
+        let stage = LoadStage::Parse;
+        self.loaded_leniently.push(Skipped { path, stage, error });
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct InitOptions {
+    pub all_translations: bool,
+    pub no_cache: bool,
 }
 
 impl LunchEnv {
     pub fn init() -> Result<Self> {
-        init_lunch()
+        Self::init_with(&InitOptions::default())
+    }
+
+    pub fn init_with(options: &InitOptions) -> Result<Self> {
+        init_lunch(options)
     }
 
-    pub fn keyword(self, keyword: &str) -> Option<Rc<Lunchable>> {
+    pub fn keyword(&self, keyword: &str) -> Option<Rc<Lunchable>> {
+        self.keyword_matches(keyword)
+            .into_iter()
+            .next()
+            .map(|m| m.item)
+    }
+
+    pub fn keyword_matches(&self, keyword: &str) -> Vec<Match<Rc<Lunchable>>> {
         info!("Searching for keyword '{}'", keyword);
-        let k = Keyword::<_, Lunchable>::new(self.lunchables);
-        k.search(keyword)
+        let matches = Keyword::<_, Lunchable>::new(self.lunchables.clone())
+            .fuzzy(self.fuzzy)
+            .matches(keyword);
+        for m in &matches {
+            debug!("Matched '{}' with score {}", m.item, m.score);
+        }
+        matches
+    }
+
+    pub fn resolve_keyword(&self, keyword: &str) -> Result<Rc<Lunchable>> {
+        self.resolve_keyword_match(keyword).map(|m| m.item)
+    }
+
+    pub fn resolve_keyword_match(&self, keyword: &str) -> Result<Match<Rc<Lunchable>>> {
+        unambiguous_match(keyword, self.keyword_matches(keyword))
+    }
+
+    pub fn search<Terms, S>(&self, terms: Terms) -> Option<Rc<Lunchable>>
+    where
+        Terms: Iterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.rank(terms).into_iter().next().map(|m| m.item)
     }
 
-    pub fn search<Terms, S>(self, terms: Terms) -> Option<Box<Lunchable>>
+    pub fn rank<Terms, S>(&self, terms: Terms) -> Vec<Match<Rc<Lunchable>>>
     where
         Terms: Iterator<Item = S>,
         S: AsRef<str>,
     {
-        for term in terms {
-            info!("Searching for term '{}'", term.as_ref());
+        let terms: Vec<_> = terms.map(|term| term.as_ref().to_owned()).collect();
+        info!("Searching for terms '{}'", terms.join(" "));
+        let matches = Rank::<_, Lunchable>::new(self.lunchables.clone())
+            .fuzzy(self.fuzzy)
+            .rank(terms.iter());
+        for m in &matches {
+            debug!("Matched '{}' with score {}", m.item, m.score);
         }
-        unimplemented!()
+        matches
     }
+
+    pub fn resolve<Terms, S>(&self, terms: Terms) -> Result<Rc<Lunchable>>
+    where
+        Terms: Iterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.resolve_match(terms).map(|m| m.item)
+    }
+
+    pub fn resolve_match<Terms, S>(&self, terms: Terms) -> Result<Match<Rc<Lunchable>>>
+    where
+        Terms: Iterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let terms: Vec<_> = terms.map(|term| term.as_ref().to_owned()).collect();
+        unambiguous_match(&terms.join(" "), self.rank(terms.iter()))
+    }
+
+    pub fn actions(&self, lunchable: &Rc<Lunchable>) -> Vec<Rc<Lunchable>> {
+        let application = application_of(lunchable);
+        self.lunchables
+            .iter()
+            .filter(|action| {
+                action
+                    .search_terms()
+                    .related
+                    .map(|related| same_lunchable(&related, &application))
+                    .unwrap_or(false)
+            })
+            .cloned()
+            .collect()
+    }
+
+    pub fn action(&self, lunchable: &Rc<Lunchable>, name: &str) -> Option<Rc<Lunchable>> {
+        info!("Searching for action '{}' of '{}'", name, lunchable);
+        Rank::<_, Lunchable>::new(self.actions(lunchable))
+            .fuzzy(self.fuzzy)
+            .search(name.split_whitespace())
+    }
+}
+
+fn application_of(lunchable: &Rc<Lunchable>) -> Rc<Lunchable> {
+    lunchable
+        .search_terms()
+        .related
+        .unwrap_or_else(|| lunchable.clone())
+}
+
+pub fn same_lunchable(a: &Rc<Lunchable>, b: &Rc<Lunchable>) -> bool {
+    &**a as *const Lunchable as *const () == &**b as *const Lunchable as *const ()
 }
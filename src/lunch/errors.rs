@@ -11,6 +11,15 @@ error_chain! {
             display("No match found for search term '{}'", term)
         }
 
+        AmbiguousMatch(term: String, candidates: Vec<String>) {
+            description("Ambiguous match")
+            display(
+                "Search term '{}' is ambiguous, candidates: '{}'",
+                term,
+                candidates.join("', '")
+            )
+        }
+
         InvalidLocale(locale: String) {
             description("Error interpreting system locale")
             display("Invalid locale '{}'", locale)
@@ -40,12 +49,30 @@ error_chain! {
             display("")
         }
 
-        InvalidCommandLine(cmd_line: String) {
+        ArgsNotAccepted(name: String) {
+            description("Application does not accept arguments")
+            display("'{}' has no field code to accept files or URLs", name)
+        }
+
+        NonLocalUrl(url: String) {
+            description("Application only accepts local files")
+            display("'{}' is not a local file and the application only accepts files", url)
+        }
+
+        MultipleFieldCodes(cmd_line: String) {
+            description("Too many field codes in command line")
+            display("Exec string '{}' has more than one file or URL field code", cmd_line)
+        }
+
+        InvalidCommandLine(cmd_line: String, position: usize, reason: String) {
             description("Unable to parse command line")
-            display("Exec string '{}' not valid", cmd_line)
+            display("Exec string '{}' not valid at position {}: {}", cmd_line, position, reason)
         }
 
-        NotDesktopEnvironment
+        TerminalNotFound {
+            description("No terminal emulator found")
+            display("No terminal emulator found, set LUNCH_TERMINAL to choose one")
+        }
 
         NoGroupsFound
 
@@ -1,6 +1,9 @@
+use std::path::Path;
 use std::result::Result as StdResult;
 use std::str::FromStr;
 
+use url::Url;
+
 use lunch::errors::*;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -9,117 +12,491 @@ pub struct Exec {
     args: Vec<Arg>,
 }
 
+#[derive(Debug, Default, Copy, Clone)]
+pub struct ExecContext<'a> {
+    pub name: &'a str,
+    pub icon: Option<&'a str>,
+    pub location: Option<&'a Path>,
+}
+
 impl Exec {
-    pub fn get_command_line(&self, user_args: Vec<String>) -> CmdLine {
-        assert!(
-            self.args
-                .iter()
-                .filter(|arg| match **arg {
-                    Arg::FieldCode => true,
-                    _ => false,
-                })
-                .count() <= 1
-        );
-        CmdLine {
+    pub fn get_command_line(
+        &self,
+        user_args: Vec<String>,
+        context: &ExecContext,
+    ) -> Result<CmdLine> {
+        if self.field_code_count() > 1 {
+            return Err(ErrorKind::MultipleFieldCodes(self.exec.clone()).into());
+        }
+        let mut args = Vec::new();
+        for arg in &self.args {
+            match *arg {
+                Arg::StaticArg(ref arg) => args.push(arg.clone()),
+                Arg::FieldCode(_) => args.extend(user_args.iter().cloned()),
+                Arg::Icon => if let Some(icon) = context.icon {
+                    args.push("--icon".to_owned());
+                    args.push(icon.to_owned());
+                },
+                Arg::Template(ref pieces) => {
+                    if pieces.iter().any(|piece| piece.field_code().is_some()) {
+                        args.extend(
+                            user_args
+                                .iter()
+                                .map(|user_arg| expand_template(pieces, Some(user_arg), context)),
+                        );
+                    } else {
+                        args.push(expand_template(pieces, None, context));
+                    }
+                }
+            }
+        }
+        Ok(CmdLine {
             cmd: self.exec.clone(),
-            args: self.args
-                .iter()
-                .flat_map(|arg| match *arg {
-                    Arg::StaticArg(ref arg) => vec![arg.clone()].into_iter(),
-                    Arg::FieldCode => user_args.clone().into_iter(),
-                })
-                .collect(),
+            args,
+        })
+    }
+
+    pub fn field_code(&self) -> Option<FieldCode> {
+        self.field_codes().first().cloned()
+    }
+
+    fn field_code_count(&self) -> usize {
+        self.field_codes().len()
+    }
+
+    fn field_codes(&self) -> Vec<FieldCode> {
+        self.args
+            .iter()
+            .flat_map(|arg| match *arg {
+                Arg::FieldCode(field_code) => vec![field_code],
+                Arg::Template(ref pieces) => pieces.iter().filter_map(Piece::field_code).collect(),
+                _ => vec![],
+            })
+            .collect()
+    }
+}
+
+fn expand_template(pieces: &[Piece], user_arg: Option<&String>, context: &ExecContext) -> String {
+    let mut arg = String::new();
+    for piece in pieces {
+        match *piece {
+            Piece::Text(ref text) => arg.push_str(text),
+            Piece::FieldCode(_) => if let Some(user_arg) = user_arg {
+                arg.push_str(user_arg)
+            },
+            Piece::Name => arg.push_str(context.name),
+            Piece::Icon => if let Some(icon) = context.icon {
+                arg.push_str(icon)
+            },
+            Piece::Location => if let Some(location) = context.location {
+                arg.push_str(&location.to_string_lossy())
+            },
         }
     }
+    arg
 }
 
-impl FromStr for Exec {
-    type Err = Error;
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ParseMode {
+    Strict,
+    Lenient,
+}
 
-    fn from_str(s: &str) -> StdResult<Self, Self::Err> {
-        let tokens: Vec<_> = split_command_line(s)?;
+impl Exec {
+    pub fn parse_with(s: &str, mode: ParseMode) -> Result<Exec> {
+        let tokens: Vec<_> = split_command_line(s, mode)?;
 
         if let [ref cmd, ref args..] = tokens[..] {
-            Ok(Exec {
+            let exec = Exec {
                 exec: cmd.to_owned(),
                 args: args.iter()
-                    .map(|arg| arg.parse())
+                    .filter_map(|arg| match Arg::from_token(arg) {
+                        Ok(Some(arg)) => Some(Ok(arg)),
+                        Ok(None) => None,
+                        Err(err) => Some(Err(err)),
+                    })
                     .collect::<Result<Vec<Arg>>>()?,
-            })
+            };
+            if exec.field_code_count() > 1 {
+                return Err(ErrorKind::MultipleFieldCodes(s.to_owned()).into());
+            }
+            Ok(exec)
         } else {
-            Err(ErrorKind::InvalidCommandLine(s.to_owned()).into())
+            Err(invalid_command_line(s, 0, "no command"))
         }
     }
 }
 
-fn split_command_line(cmd_line: &str) -> Result<Vec<String>> {
-    let mut slices = Vec::new();
-    let mut chars = cmd_line.chars();
-    let mut token: String = String::new();
-    let mut quote = false;
-    while let Some(c) = chars.next() {
-        match c {
-            '\\' => {
-                if let Some(c) = chars.next() {
-                    match c {
-                        'n' => token.push('\n'),
-                        't' => token.push('\t'),
-                        _ => token.push(c),
+impl FromStr for Exec {
+    type Err = Error;
+
+    fn from_str(s: &str) -> StdResult<Self, Self::Err> {
+        match Exec::parse_with(s, ParseMode::Strict) {
+            Err(Error(ErrorKind::InvalidCommandLine(_, position, reason), _)) => {
+                debug!(
+                    "Exec string '{}' not valid at position {}: {}; parsing leniently",
+                    s, position, reason
+                );
+                Exec::parse_with(s, ParseMode::Lenient)
+            }
+            result => result,
+        }
+    }
+}
+
+const RESERVED: &str = "\t\n\"'\\><~|&;$*?#()`";
+
+fn invalid_command_line(cmd_line: &str, position: usize, reason: &str) -> Error {
+    ErrorKind::InvalidCommandLine(cmd_line.to_owned(), position, reason.to_owned()).into()
+}
+
+fn unescape_string(s: &str, mode: ParseMode) -> Result<Vec<(usize, char)>> {
+    let mut unescaped = Vec::new();
+    let mut chars = s.chars().enumerate();
+    while let Some((pos, c)) = chars.next() {
+        if c != '\\' {
+            unescaped.push((pos, c));
+            continue;
+        }
+        match chars.next() {
+            Some((_, 's')) => unescaped.push((pos, ' ')),
+            Some((_, 'n')) => unescaped.push((pos, '\n')),
+            Some((_, 't')) => unescaped.push((pos, '\t')),
+            Some((_, 'r')) => unescaped.push((pos, '\r')),
+            Some((_, '\\')) => unescaped.push((pos, '\\')),
+            Some((next_pos, c)) if mode == ParseMode::Lenient => {
+                unescaped.push((pos, '\\'));
+                unescaped.push((next_pos, c));
+            }
+            None if mode == ParseMode::Lenient => unescaped.push((pos, '\\')),
+            Some(_) => return Err(invalid_command_line(s, pos, "invalid escape sequence")),
+            None => return Err(invalid_command_line(s, pos, "trailing backslash")),
+        }
+    }
+    Ok(unescaped)
+}
+
+fn split_command_line(cmd_line: &str, mode: ParseMode) -> Result<Vec<String>> {
+    let lenient = mode == ParseMode::Lenient;
+    let mut chars = unescape_string(cmd_line, mode)?.into_iter().peekable();
+    let mut tokens = Vec::new();
+    let mut token = String::new();
+    let mut in_token = false;
+    let mut quote = None;
+    while let Some((pos, c)) = chars.next() {
+        if quote.is_some() {
+            match c {
+                '"' => {
+                    quote = None;
+                    match chars.peek() {
+                        Some(&(next_pos, next)) if next != ' ' && !lenient => {
+                            return Err(invalid_command_line(
+                                cmd_line,
+                                next_pos,
+                                "expected space after closing quote",
+                            ))
+                        }
+                        _ => {}
                     }
                 }
+                '\\' => match chars.next() {
+                    Some((_, c)) if c == '"' || c == '`' || c == '$' || c == '\\' => token.push(c),
+                    Some((_, c)) if lenient => {
+                        token.push('\\');
+                        token.push(c);
+                    }
+                    None if lenient => token.push('\\'),
+                    _ => {
+                        return Err(invalid_command_line(
+                            cmd_line,
+                            pos,
+                            "invalid escape in quoted argument",
+                        ))
+                    }
+                },
+                '`' | '$' if !lenient => {
+                    return Err(invalid_command_line(
+                        cmd_line,
+                        pos,
+                        "reserved character must be escaped in quoted argument",
+                    ))
+                }
+                _ => token.push(c),
             }
+            continue;
+        }
+        match c {
             ' ' => {
-                if quote {
-                    token.push(c);
-                } else {
-                    slices.push(token.clone());
+                if in_token {
+                    tokens.push(token.clone());
                     token.clear();
+                    in_token = false;
                 }
             }
+            '\t' | '\n' if lenient => {
+                if in_token {
+                    tokens.push(token.clone());
+                    token.clear();
+                    in_token = false;
+                }
+            }
+            '"' if !in_token || lenient => {
+                quote = Some(pos);
+                in_token = true;
+            }
+            '\\' if lenient => {
+                if let Some((_, c)) = chars.next() {
+                    token.push(c);
+                }
+                in_token = true;
+            }
             '"' => {
-                quote = !quote;
+                return Err(invalid_command_line(
+                    cmd_line,
+                    pos,
+                    "quote must enclose the whole argument",
+                ))
+            }
+            c if RESERVED.contains(c) && !lenient => {
+                return Err(invalid_command_line(
+                    cmd_line,
+                    pos,
+                    "reserved character must be quoted",
+                ))
             }
             _ => {
                 token.push(c);
+                in_token = true;
             }
         }
     }
-    if !token.is_empty() {
-        slices.push(token);
+    if let Some(pos) = quote {
+        if !lenient {
+            return Err(invalid_command_line(cmd_line, pos, "unterminated quote"));
+        }
+    }
+    if in_token {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod split_command_line_tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn position(result: Result<Vec<String>>) -> Option<usize> {
+        match result {
+            Err(Error(ErrorKind::InvalidCommandLine(_, position, _), _)) => Some(position),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn repeated_spaces() {
+        assert_that!(split_command_line("echo  -n   -e ", ParseMode::Strict).unwrap())
+            .is_equal_to(vec!["echo".to_owned(), "-n".to_owned(), "-e".to_owned()]);
+    }
+
+    #[test]
+    fn empty_quoted_arg() {
+        assert_that!(split_command_line(r#"echo "" -n"#, ParseMode::Strict).unwrap())
+            .is_equal_to(vec!["echo".to_owned(), "".to_owned(), "-n".to_owned()]);
+    }
+
+    #[test]
+    fn quoted_escapes() {
+        let cmd_line = r#"sh -c "echo \\"hi\\" \\$HOME \\`pwd\\` \\\\""#;
+        assert_that!(split_command_line(cmd_line, ParseMode::Strict).unwrap()).is_equal_to(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            r#"echo "hi" $HOME `pwd` \"#.to_owned(),
+        ]);
+    }
+
+    #[test]
+    fn string_escapes() {
+        assert_that!(split_command_line(r#"echo "a\sb\tc""#, ParseMode::Strict).unwrap())
+            .is_equal_to(vec!["echo".to_owned(), "a b\tc".to_owned()]);
+        assert_that!(split_command_line(r"echo a\sb", ParseMode::Strict).unwrap())
+            .is_equal_to(vec!["echo".to_owned(), "a".to_owned(), "b".to_owned()]);
+    }
+
+    #[test]
+    fn strict_reserved_unquoted() {
+        assert_that!(position(split_command_line("echo a|b", ParseMode::Strict)))
+            .is_some()
+            .is_equal_to(6);
+        assert_that!(position(split_command_line("sh -c 'echo'", ParseMode::Strict)))
+            .is_some()
+            .is_equal_to(6);
+    }
+
+    #[test]
+    fn strict_unescaped_in_quotes() {
+        assert_that!(position(split_command_line(r#"sh -c "echo $HOME""#, ParseMode::Strict)))
+            .is_some()
+            .is_equal_to(12);
+    }
+
+    #[test]
+    fn strict_invalid_escape() {
+        assert_that!(position(split_command_line(r"/opt/Echo\ 2/echo", ParseMode::Strict)))
+            .is_some()
+            .is_equal_to(9);
+        assert_that!(position(split_command_line(r#"echo "\\a""#, ParseMode::Strict)))
+            .is_some()
+            .is_equal_to(6);
+    }
+
+    #[test]
+    fn strict_partial_quote() {
+        assert_that!(position(split_command_line(r#"echo --x="y""#, ParseMode::Strict)))
+            .is_some()
+            .is_equal_to(9);
+        assert_that!(position(split_command_line(r#"echo "y"z"#, ParseMode::Strict)))
+            .is_some()
+            .is_equal_to(8);
+    }
+
+    #[test]
+    fn strict_unterminated_quote() {
+        assert_that!(position(split_command_line(r#"echo "abc"#, ParseMode::Strict)))
+            .is_some()
+            .is_equal_to(5);
+    }
+
+    #[test]
+    fn lenient() {
+        let cmd_line = r#"sh -c "echo $HOME" 'a' --x="y z""#;
+        assert_that!(split_command_line(cmd_line, ParseMode::Lenient).unwrap()).is_equal_to(vec![
+            "sh".to_owned(),
+            "-c".to_owned(),
+            "echo $HOME".to_owned(),
+            "'a'".to_owned(),
+            "--x=y z".to_owned(),
+        ]);
+        let cmd_line = r#"/opt/Echo\ 2/echo "\"a\"""#;
+        assert_that!(split_command_line(cmd_line, ParseMode::Lenient).unwrap())
+            .is_equal_to(vec!["/opt/Echo 2/echo".to_owned(), r#""a""#.to_owned()]);
+    }
+
+    #[test]
+    fn parse_falls_back_to_lenient() {
+        let exec: Exec = r#"sh -c "echo $HOME""#.parse().unwrap();
+        assert_that!(exec.exec).is_equal_to("sh".to_owned());
+        assert_that!(Exec::parse_with(r#"sh -c "echo $HOME""#, ParseMode::Strict)).is_err();
+    }
+
+    #[test]
+    fn parse_empty() {
+        assert_that!(Exec::parse_with("  ", ParseMode::Lenient)).is_err();
     }
-    Ok(slices)
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 enum Arg {
-    FieldCode,
+    FieldCode(FieldCode),
+    Icon,
     StaticArg(String),
+    Template(Vec<Piece>),
 }
 
-impl FromStr for Arg {
-    type Err = Error;
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Piece {
+    Text(String),
+    FieldCode(FieldCode),
+    Name,
+    Icon,
+    Location,
+}
 
-    fn from_str(s: &str) -> StdResult<Self, Self::Err> {
-        match s {
-            "%f" | "%F" | "%u" | "%U" => Ok(Arg::FieldCode),
-            _ => Ok(Arg::StaticArg(s.to_owned())),
+impl Piece {
+    fn field_code(&self) -> Option<FieldCode> {
+        match *self {
+            Piece::FieldCode(field_code) => Some(field_code),
+            _ => None,
         }
     }
 }
 
+impl Arg {
+    fn from_token(token: &str) -> Result<Option<Arg>> {
+        match token {
+            "%f" => return Ok(Some(Arg::FieldCode(FieldCode::SingleFile))),
+            "%F" => return Ok(Some(Arg::FieldCode(FieldCode::MultipleFiles))),
+            "%u" => return Ok(Some(Arg::FieldCode(FieldCode::SingleUrl))),
+            "%U" => return Ok(Some(Arg::FieldCode(FieldCode::MultipleUrls))),
+            "%i" => return Ok(Some(Arg::Icon)),
+            _ => {}
+        }
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = token.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                text.push(c);
+                continue;
+            }
+            let piece = match chars.next() {
+                Some('%') | None => {
+                    text.push('%');
+                    continue;
+                }
+                Some('f') => Piece::FieldCode(FieldCode::SingleFile),
+                Some('u') => Piece::FieldCode(FieldCode::SingleUrl),
+                Some('F') => {
+                    return Err(ErrorKind::UnexpectedArgType(FieldCode::MultipleFiles).into())
+                }
+                Some('U') => {
+                    return Err(ErrorKind::UnexpectedArgType(FieldCode::MultipleUrls).into())
+                }
+                Some('c') => Piece::Name,
+                Some('i') => Piece::Icon,
+                Some('k') => Piece::Location,
+                Some('d') | Some('D') | Some('n') | Some('N') | Some('v') | Some('m') => continue,
+                Some(c) => {
+                    warn!("Unknown field code '%{}' in Exec arg '{}'", c, token);
+                    text.push('%');
+                    text.push(c);
+                    continue;
+                }
+            };
+            if !text.is_empty() {
+                pieces.push(Piece::Text(text.clone()));
+                text.clear();
+            }
+            pieces.push(piece);
+        }
+        if pieces.is_empty() {
+            if text.is_empty() && !token.is_empty() {
+                return Ok(None);
+            }
+            return Ok(Some(Arg::StaticArg(text)));
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(Some(Arg::Template(pieces)))
+    }
+}
+
 #[cfg(test)]
 mod exec_tests {
     use super::*;
     use spectral::prelude::*;
 
     #[test]
-    #[should_panic]
     fn exec_multiple_field_codes() {
         let exec = Exec {
             exec: "echo".to_owned(),
-            args: vec![Arg::FieldCode, Arg::FieldCode],
+            args: vec![
+                Arg::FieldCode(FieldCode::SingleFile),
+                Arg::FieldCode(FieldCode::SingleFile),
+            ],
         };
-        exec.get_command_line(vec![]);
+        assert_that!(exec.get_command_line(vec![], &ExecContext::default())).is_err();
     }
 
     #[test]
@@ -128,7 +505,7 @@ mod exec_tests {
             exec: "echo".to_owned(),
             args: vec![],
         };
-        let cmd_line = exec.get_command_line(vec![]);
+        let cmd_line = exec.get_command_line(vec![], &ExecContext::default()).unwrap();
         assert_that!(cmd_line.args).is_equal_to(vec![] as Vec<String>);
         assert_that!(cmd_line.cmd).is_equal_to("echo".to_owned());
     }
@@ -139,7 +516,7 @@ mod exec_tests {
             exec: "echo".to_owned(),
             args: vec![Arg::StaticArg("-n".into()), Arg::StaticArg("-e".into())],
         };
-        let cmd_line = exec.get_command_line(vec![]);
+        let cmd_line = exec.get_command_line(vec![], &ExecContext::default()).unwrap();
         assert_that!(cmd_line.args).is_equal_to(vec!["-n".to_owned(), "-e".to_owned()]);
         assert_that!(cmd_line.cmd).is_equal_to("echo".to_owned());
     }
@@ -148,9 +525,11 @@ mod exec_tests {
     fn exec_field_code_args() {
         let exec = Exec {
             exec: "echo".to_owned(),
-            args: vec![Arg::FieldCode],
+            args: vec![Arg::FieldCode(FieldCode::SingleFile)],
         };
-        let cmd_line = exec.get_command_line(vec!["-n".to_owned(), "-e".to_owned()]);
+        let cmd_line = exec
+            .get_command_line(vec!["-n".to_owned(), "-e".to_owned()], &ExecContext::default())
+            .unwrap();
         assert_that!(cmd_line.args).is_equal_to(vec!["-n".to_owned(), "-e".to_owned()]);
         assert_that!(cmd_line.cmd).is_equal_to("echo".to_owned());
     }
@@ -161,11 +540,13 @@ mod exec_tests {
             exec: "echo".to_owned(),
             args: vec![
                 Arg::StaticArg("-n".into()),
-                Arg::FieldCode,
+                Arg::FieldCode(FieldCode::SingleFile),
                 Arg::StaticArg("-e".into()),
             ],
         };
-        let cmd_line = exec.get_command_line(vec!["-E".to_owned()]);
+        let cmd_line = exec
+            .get_command_line(vec!["-E".to_owned()], &ExecContext::default())
+            .unwrap();
         assert_that!(cmd_line.args).is_equal_to(vec![
             "-n".to_owned(),
             "-E".to_owned(),
@@ -197,7 +578,7 @@ mod exec_tests {
         assert_that!(&exec.exec).is_equal_to("echo".to_owned());
         assert_that!(exec.args).is_equal_to(vec![
             Arg::StaticArg("-n".to_owned()),
-            Arg::FieldCode,
+            Arg::FieldCode(FieldCode::SingleFile),
             Arg::StaticArg("-e".to_owned()),
         ]);
     }
@@ -208,7 +589,7 @@ mod exec_tests {
         assert_that!(&exec.exec).is_equal_to(r"/opt/Echo 2/echo".to_owned());
         assert_that!(exec.args).is_equal_to(vec![
             Arg::StaticArg("-n".to_owned()),
-            Arg::FieldCode,
+            Arg::FieldCode(FieldCode::SingleFile),
             Arg::StaticArg("-e".to_owned()),
         ]);
     }
@@ -221,12 +602,168 @@ mod exec_tests {
         assert_that!(&exec.exec).is_equal_to("/opt/Echo 2/echo".to_owned());
         assert_that!(exec.args).is_equal_to(vec![
             Arg::StaticArg("-n".to_owned()),
-            Arg::FieldCode,
+            Arg::FieldCode(FieldCode::SingleFile),
             Arg::StaticArg("-e".to_owned()),
             Arg::StaticArg("arg with spaces".to_owned()),
             Arg::StaticArg("-v".to_owned()),
         ]);
     }
+
+    #[test]
+    fn exec_parse_percent() {
+        let exec: Exec = "echo 100%% %%f".parse().unwrap();
+        assert_that!(exec.args).is_equal_to(vec![
+            Arg::StaticArg("100%".to_owned()),
+            Arg::StaticArg("%f".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn exec_parse_deprecated() {
+        let exec: Exec = "echo %d -n %N %m".parse().unwrap();
+        assert_that!(exec.args).is_equal_to(vec![Arg::StaticArg("-n".to_owned())]);
+    }
+
+    #[test]
+    fn exec_parse_embedded() {
+        let exec: Exec = "echo --file=%f --name=%c".parse().unwrap();
+        assert_that!(exec.args).is_equal_to(vec![
+            Arg::Template(vec![
+                Piece::Text("--file=".to_owned()),
+                Piece::FieldCode(FieldCode::SingleFile),
+            ]),
+            Arg::Template(vec![Piece::Text("--name=".to_owned()), Piece::Name]),
+        ]);
+    }
+
+    #[test]
+    fn exec_parse_embedded_multiple_files() {
+        let exec = "echo --files=%F".parse::<Exec>();
+        assert_that!(exec).is_err();
+    }
+
+    #[test]
+    fn exec_parse_multiple_field_codes() {
+        let exec = "echo %f --file=%u".parse::<Exec>();
+        assert_that!(exec).is_err();
+    }
+
+    #[test]
+    fn exec_expand_context() {
+        let exec: Exec = "echo %i --name=%c %k %u".parse().unwrap();
+        let location = Path::new("/usr/share/applications/echo.desktop");
+        let context = ExecContext {
+            name: "Echo",
+            icon: Some("echo-icon"),
+            location: Some(location),
+        };
+        let cmd_line = exec.get_command_line(vec!["url".to_owned()], &context)
+            .unwrap();
+        assert_that!(cmd_line.args).is_equal_to(vec![
+            "--icon".to_owned(),
+            "echo-icon".to_owned(),
+            "--name=Echo".to_owned(),
+            "/usr/share/applications/echo.desktop".to_owned(),
+            "url".to_owned(),
+        ]);
+    }
+
+    #[test]
+    fn exec_expand_no_icon() {
+        let exec: Exec = "echo %i -n".parse().unwrap();
+        let cmd_line = exec.get_command_line(vec![], &ExecContext::default())
+            .unwrap();
+        assert_that!(cmd_line.args).is_equal_to(vec!["-n".to_owned()]);
+    }
+
+    #[test]
+    fn exec_expand_embedded_field_code() {
+        let exec: Exec = "echo --file=%f".parse().unwrap();
+        let cmd_line = exec.get_command_line(vec!["a.png".to_owned()], &ExecContext::default())
+            .unwrap();
+        assert_that!(cmd_line.args).is_equal_to(vec!["--file=a.png".to_owned()]);
+        let cmd_line = exec.get_command_line(vec![], &ExecContext::default())
+            .unwrap();
+        assert_that!(cmd_line.args).is_equal_to(vec![] as Vec<String>);
+    }
+}
+
+pub fn absolute_args(args: Vec<String>) -> Result<Vec<String>> {
+    if args.iter().all(|arg| is_url(arg) || Path::new(arg).is_absolute()) {
+        return Ok(args);
+    }
+    let cwd = ::std::env::current_dir().chain_err(|| "Error getting current directory")?;
+    Ok(args.into_iter()
+        .map(|arg| {
+            if is_url(&arg) || Path::new(&arg).is_absolute() {
+                arg
+            } else {
+                cwd.join(arg).to_string_lossy().into_owned()
+            }
+        })
+        .collect())
+}
+
+const OPAQUE_SCHEMES: &[&str] = &["mailto", "news", "tel", "sms", "magnet", "urn", "data"];
+
+fn is_url(arg: &str) -> bool {
+    let end = match arg.find(':') {
+        Some(end) if end > 1 => end,
+        _ => return false,
+    };
+    let scheme = &arg[..end];
+    let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.');
+    valid_scheme
+        && Url::parse(arg).is_ok()
+        && (arg[end..].starts_with("://")
+            || OPAQUE_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()))
+}
+
+#[cfg(test)]
+mod absolute_args_tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn relative_path() {
+        let cwd = ::std::env::current_dir().unwrap();
+        assert_that!(absolute_args(vec!["a.png".to_owned()]).unwrap())
+            .is_equal_to(vec![cwd.join("a.png").to_string_lossy().into_owned()]);
+    }
+
+    #[test]
+    fn absolute_path() {
+        assert_that!(absolute_args(vec!["/tmp/a.png".to_owned()]).unwrap())
+            .is_equal_to(vec!["/tmp/a.png".to_owned()]);
+    }
+
+    #[test]
+    fn urls_untouched() {
+        let args = vec![
+            "https://example.com/a.png".to_owned(),
+            "file:///tmp/a.png".to_owned(),
+            "mailto:someone@example.com".to_owned(),
+        ];
+        assert_that!(absolute_args(args.clone()).unwrap()).is_equal_to(args);
+    }
+
+    #[test]
+    fn not_url() {
+        assert_that!(is_url("a.png")).is_false();
+        assert_that!(is_url("./b:c.png")).is_false();
+        assert_that!(is_url("c:")).is_false();
+        assert_that!(is_url("foo:bar.png")).is_false();
+    }
+
+    #[test]
+    fn relative_path_with_colon() {
+        let cwd = ::std::env::current_dir().unwrap();
+        assert_that!(absolute_args(vec!["foo:bar.png".to_owned()]).unwrap())
+            .is_equal_to(vec![cwd.join("foo:bar.png").to_string_lossy().into_owned()]);
+    }
 }
 
 #[derive(Debug)]
@@ -244,67 +781,98 @@ pub enum FieldCode {
 }
 
 impl FieldCode {
-    pub fn extract_field_code(exec: &str) -> Option<FieldCode> {
-        let field_code = if exec.contains("%f") {
-            FieldCode::SingleFile
-        } else if exec.contains("%F") {
-            FieldCode::MultipleFiles
-        } else if exec.contains("%u") {
-            FieldCode::SingleUrl
-        } else if exec.contains("%U") {
-            FieldCode::MultipleUrls
-        } else {
-            return None;
-        };
-        Some(field_code)
+    pub fn convert_args(&self, args: Vec<String>) -> Result<Vec<String>> {
+        use self::FieldCode::*;
+        absolute_args(args)?
+            .into_iter()
+            .map(|arg| match *self {
+                SingleFile | MultipleFiles => to_path(arg),
+                SingleUrl | MultipleUrls => to_url(arg),
+            })
+            .collect()
     }
 
-    pub fn expand_exec(&self, exec: &Exec, args: Vec<String>) -> Vec<CmdLine> {
+    pub fn expand_exec(
+        &self,
+        exec: &Exec,
+        args: Vec<String>,
+        context: &ExecContext,
+    ) -> Result<Vec<CmdLine>> {
         use self::FieldCode::*;
         if args.is_empty() {
-            vec![exec.get_command_line(args)]
+            Ok(vec![exec.get_command_line(args, context)?])
         } else {
             match *self {
                 SingleFile | SingleUrl => args.into_iter()
-                    .map(|arg| exec.get_command_line(vec![arg]))
+                    .map(|arg| exec.get_command_line(vec![arg], context))
                     .collect(),
-                MultipleFiles | MultipleUrls => vec![exec.get_command_line(args)],
+                MultipleFiles | MultipleUrls => Ok(vec![exec.get_command_line(args, context)?]),
             }
         }
     }
 }
 
+fn to_path(arg: String) -> Result<String> {
+    if !is_url(&arg) {
+        return Ok(arg);
+    }
+    match Url::parse(&arg).map(|url| url.to_file_path()) {
+        Ok(Ok(path)) => Ok(path.to_string_lossy().into_owned()),
+        _ => Err(ErrorKind::NonLocalUrl(arg).into()),
+    }
+}
+
+fn to_url(arg: String) -> Result<String> {
+    if is_url(&arg) {
+        return Ok(arg);
+    }
+    Url::from_file_path(&arg)
+        .map(|url| url.into_string())
+        .map_err(|_| format!("Cannot convert '{}' to a URL", arg).into())
+}
+
 #[cfg(test)]
 mod field_code_tests {
     use super::*;
     use spectral::prelude::*;
 
     #[test]
-    fn test_extract() {
-        assert_that!(FieldCode::extract_field_code("/bin/echo %f"))
+    fn test_field_code() {
+        let field_code = |exec: &str| exec.parse::<Exec>().unwrap().field_code();
+        assert_that!(field_code("/bin/echo %f"))
             .is_some()
             .is_equal_to(FieldCode::SingleFile);
-        assert_that!(FieldCode::extract_field_code("/bin/echo %F"))
+        assert_that!(field_code("/bin/echo %F"))
             .is_some()
             .is_equal_to(FieldCode::MultipleFiles);
-        assert_that!(FieldCode::extract_field_code("/bin/echo %u"))
+        assert_that!(field_code("/bin/echo %u"))
             .is_some()
             .is_equal_to(FieldCode::SingleUrl);
-        assert_that!(FieldCode::extract_field_code("/bin/echo %U"))
+        assert_that!(field_code("/bin/echo %U"))
             .is_some()
             .is_equal_to(FieldCode::MultipleUrls);
+        assert_that!(field_code("/bin/echo --file=%u"))
+            .is_some()
+            .is_equal_to(FieldCode::SingleUrl);
+        assert_that!(field_code(r#"/bin/echo "%F""#))
+            .is_some()
+            .is_equal_to(FieldCode::MultipleFiles);
 
-        assert_that!(FieldCode::extract_field_code("/bin/echo %G")).is_none();
-        assert_that!(FieldCode::extract_field_code("/bin/echo")).is_none();
+        assert_that!(field_code("/bin/echo %G")).is_none();
+        assert_that!(field_code("/bin/echo %%f")).is_none();
+        assert_that!(field_code("/bin/echo 100%% %%U")).is_none();
+        assert_that!(field_code("/bin/echo")).is_none();
     }
 
     #[test]
     fn field_code_single_file_no_args() {
         let exec = Exec {
             exec: "echo".to_owned(),
-            args: vec![Arg::FieldCode],
+            args: vec![Arg::FieldCode(FieldCode::SingleFile)],
         };
-        let cmd_lines = FieldCode::SingleFile.expand_exec(&exec, vec![]);
+        let cmd_lines = FieldCode::SingleFile
+            .expand_exec(&exec, vec![], &ExecContext::default())
+            .unwrap();
         assert_that!(cmd_lines.len()).is_equal_to(1);
         let cmd_line = &cmd_lines[0];
         assert_that!(cmd_line.args).is_equal_to(vec![] as Vec<String>);
@@ -315,9 +883,11 @@ mod field_code_tests {
     fn field_code_single_file_single_arg() {
         let exec = Exec {
             exec: "echo".to_owned(),
-            args: vec![Arg::FieldCode],
+            args: vec![Arg::FieldCode(FieldCode::SingleFile)],
         };
-        let cmd_lines = FieldCode::SingleFile.expand_exec(&exec, vec!["arg".to_owned()]);
+        let cmd_lines = FieldCode::SingleFile
+            .expand_exec(&exec, vec!["arg".to_owned()], &ExecContext::default())
+            .unwrap();
         assert_that!(cmd_lines.len()).is_equal_to(1);
         let cmd_line = &cmd_lines[0];
         assert_that!(cmd_line.args).is_equal_to(vec!["arg".to_owned()]);
@@ -328,10 +898,11 @@ mod field_code_tests {
     fn field_code_single_file_multiple_args() {
         let exec = Exec {
             exec: "echo".to_owned(),
-            args: vec![Arg::FieldCode],
+            args: vec![Arg::FieldCode(FieldCode::SingleFile)],
         };
-        let cmd_lines =
-            FieldCode::SingleFile.expand_exec(&exec, vec!["arg1".to_owned(), "arg2".to_owned()]);
+        let cmd_lines = FieldCode::SingleFile
+            .expand_exec(&exec, vec!["arg1".to_owned(), "arg2".to_owned()], &ExecContext::default())
+            .unwrap();
         assert_that!(cmd_lines.len()).is_equal_to(2);
         let cmd_line = &cmd_lines[0];
         assert_that!(cmd_line.args).is_equal_to(vec!["arg1".to_owned()]);
@@ -345,9 +916,11 @@ mod field_code_tests {
     fn field_code_multiple_files_single_arg() {
         let exec = Exec {
             exec: "echo".to_owned(),
-            args: vec![Arg::FieldCode],
+            args: vec![Arg::FieldCode(FieldCode::MultipleFiles)],
         };
-        let cmd_lines = FieldCode::MultipleFiles.expand_exec(&exec, vec!["arg".to_owned()]);
+        let cmd_lines = FieldCode::MultipleFiles
+            .expand_exec(&exec, vec!["arg".to_owned()], &ExecContext::default())
+            .unwrap();
         assert_that!(cmd_lines.len()).is_equal_to(1);
         let cmd_line = &cmd_lines[0];
         assert_that!(cmd_line.args).is_equal_to(vec!["arg".to_owned()]);
@@ -358,13 +931,57 @@ mod field_code_tests {
     fn field_code_multiple_files_multiple_args() {
         let exec = Exec {
             exec: "echo".to_owned(),
-            args: vec![Arg::FieldCode],
+            args: vec![Arg::FieldCode(FieldCode::MultipleFiles)],
         };
-        let cmd_lines =
-            FieldCode::MultipleFiles.expand_exec(&exec, vec!["arg1".to_owned(), "arg2".to_owned()]);
+        let cmd_lines = FieldCode::MultipleFiles
+            .expand_exec(&exec, vec!["arg1".to_owned(), "arg2".to_owned()], &ExecContext::default())
+            .unwrap();
         assert_that!(cmd_lines.len()).is_equal_to(1);
         let cmd_line = &cmd_lines[0];
         assert_that!(cmd_line.args).is_equal_to(vec!["arg1".to_owned(), "arg2".to_owned()]);
         assert_that!(cmd_line.cmd).is_equal_to("echo".to_owned());
     }
 }
+
+#[cfg(test)]
+mod convert_args_tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn path_to_url() {
+        let args = vec!["/tmp/a b.png".to_owned(), "https://example.com/".to_owned()];
+        assert_that!(FieldCode::MultipleUrls.convert_args(args).unwrap()).is_equal_to(vec![
+            "file:///tmp/a%20b.png".to_owned(),
+            "https://example.com/".to_owned(),
+        ]);
+    }
+
+    #[test]
+    fn url_to_path() {
+        let args = vec!["file:///tmp/a%20b.png".to_owned(), "/tmp/c.png".to_owned()];
+        assert_that!(FieldCode::MultipleFiles.convert_args(args).unwrap())
+            .is_equal_to(vec!["/tmp/a b.png".to_owned(), "/tmp/c.png".to_owned()]);
+    }
+
+    #[test]
+    fn relative_path_to_url() {
+        let cwd = ::std::env::current_dir().unwrap();
+        let url = Url::from_file_path(cwd.join("a.png")).unwrap().into_string();
+        assert_that!(FieldCode::SingleUrl.convert_args(vec!["a.png".to_owned()]).unwrap())
+            .is_equal_to(vec![url]);
+    }
+
+    #[test]
+    fn non_local_url() {
+        let args = vec!["https://example.com/a.png".to_owned()];
+        match FieldCode::SingleFile.convert_args(args) {
+            Err(Error(ErrorKind::NonLocalUrl(url), _)) => {
+                assert_that!(url).is_equal_to("https://example.com/a.png".to_owned())
+            }
+            _ => panic!("expected non-local URL error"),
+        }
+        let args = vec!["file://example.com/a.png".to_owned()];
+        assert_that!(FieldCode::SingleFile.convert_args(args)).is_err();
+    }
+}
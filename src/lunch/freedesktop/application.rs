@@ -11,10 +11,12 @@ use users::os::unix::GroupExt;
 
 use super::desktopfile::DesktopFile;
 use super::entry::*;
+use super::terminal::Terminal;
+use lunch::logs::log_path;
 use lunch::errors::*;
-use lunch::exec::{Exec, FieldCode};
-use lunch::{Io, Launch, Lunchable, Options, Search};
-use lunch::search::SearchTerms;
+use lunch::exec::{CmdLine, Exec, ExecContext};
+use lunch::{Details, Launch, Lunchable, Search};
+use lunch::search::{Field, SearchTerms};
 
 #[derive(Debug)]
 pub struct Application {
@@ -32,18 +34,23 @@ impl TryFrom<DesktopFile> for Application {
         );
         let exec = desktop_file.desktop_entry.exec.unwrap_or("".to_owned());
         if exec.trim().is_empty() {
-            return Err(ErrorKind::InvalidCommandLine(exec).into());
+            return Err(ErrorKind::InvalidCommandLine(exec, 0, "empty Exec key".to_owned()).into());
         }
 
         let app_data = Rc::new(ApplicationPart {
             name: desktop_file.desktop_entry.name,
+            generic_name: desktop_file.desktop_entry.generic_name,
             icon: desktop_file.desktop_entry.icon,
             comment: desktop_file.desktop_entry.comment,
+            categories: desktop_file.desktop_entry.categories,
             keywords: desktop_file.desktop_entry.keywords,
-            field_code: FieldCode::extract_field_code(&exec),
+            translations: desktop_file.desktop_entry.translations,
             exec: exec.parse()?,
             try_exec: desktop_file.desktop_entry.try_exec.map(From::from),
             path: desktop_file.desktop_entry.path.map(From::from),
+            terminal: desktop_file.desktop_entry.terminal,
+            id: desktop_file.id,
+            location: desktop_file.path,
         });
         let actions = desktop_file
             .actions
@@ -78,16 +85,29 @@ impl Application {
 #[derive(Debug)]
 struct ApplicationPart {
     pub name: String,
+    pub generic_name: Option<String>,
     pub icon: Option<String>,
     pub comment: Option<String>,
+    pub categories: Vec<String>,
     pub keywords: Vec<String>,
+    pub translations: Translations,
     pub exec: Exec,
-    pub field_code: Option<FieldCode>,
     pub try_exec: Option<PathBuf>,
     pub path: Option<PathBuf>,
+    pub terminal: bool,
+    pub id: Option<String>,
+    pub location: Option<PathBuf>,
 }
 
 impl ApplicationPart {
+    fn exec_context(&self) -> ExecContext {
+        ExecContext {
+            name: &self.name,
+            icon: self.icon(),
+            location: self.location(),
+        }
+    }
+
     pub fn can_exec(&self) -> bool {
         self.try_exec
             .as_ref()
@@ -96,7 +116,7 @@ impl ApplicationPart {
     }
 }
 
-fn can_exec(try_exec: &Path, env_path: Option<OsString>) -> bool {
+pub fn can_exec(try_exec: &Path, env_path: Option<OsString>) -> bool {
     if try_exec.is_absolute() {
         try_exec.exists() && is_executable(try_exec)
     } else if let Some(paths) = env_path {
@@ -309,57 +329,78 @@ impl Display for ApplicationPart {
 }
 
 impl Launch for ApplicationPart {
-    fn launch(&self, args: Vec<String>) -> Error {
+    fn command_lines(&self, args: Vec<String>) -> Result<Vec<CmdLine>> {
         if !self.can_exec() {
-            return ErrorKind::ApplicationNotFound.into();
+            return Err(ErrorKind::ApplicationNotFound.into());
         }
-        info!("Launching '{}'...", self);
         if let Some(ref path) = self.try_exec {
             let path = Path::new(path);
             if !path.exists() {
-                return ErrorKind::ApplicationNotFound.into();
+                return Err(ErrorKind::ApplicationNotFound.into());
             }
         }
 
-        if let Some(field_code) = self.field_code {
-            let cmd_lines = field_code.expand_exec(&self.exec, args);
-            let children = cmd_lines
-                .into_iter()
-                .map(|cmd_line| {
-                    self.spawn(
-                        cmd_line,
-                        self.path.as_ref().map(|path| path.as_path()),
-                        &Options { io: Io::Suppress },
-                    )
-                })
-                .collect::<Result<Vec<_>>>();
-            match children {
-                Ok(_) => {
-                    ::std::process::exit(0);
-                }
-                Err(err) => err.into(),
-            }
-        } else {
-            let cmd_line = self.exec.get_command_line(vec![]);
-            let opt = Options { io: Io::Inherit };
-            self.exec(
-                cmd_line,
-                self.path.as_ref().map(|path| path.as_path()),
-                &opt,
-            )
-        }
+        command_lines(
+            self,
+            &self.exec,
+            &self.exec_context(),
+            self.terminal,
+            args,
+        )
+    }
+
+    fn work_dir(&self) -> Option<&Path> {
+        self.path.as_ref().map(|path| path.as_path())
+    }
+
+    fn log_file(&self) -> Option<PathBuf> {
+        log_file(self)
+    }
+}
+
+impl Details for ApplicationPart {
+    fn id(&self) -> Option<&str> {
+        self.id.as_ref().map(|id| id.as_str())
+    }
+
+    fn icon(&self) -> Option<&str> {
+        self.icon.as_ref().map(|icon| icon.as_str())
+    }
+
+    fn location(&self) -> Option<&Path> {
+        self.location.as_ref().map(|location| location.as_path())
+    }
+
+    fn terminal(&self) -> bool {
+        self.terminal
     }
 }
 
 impl Search for ApplicationPart {
     fn search_terms(&self) -> SearchTerms {
-        let mut terms = vec![self.name.clone()];
+        use std::borrow::{Borrow, Cow};
+        let mut terms = vec![(Field::Name, Cow::Borrowed(self.name.as_str()))];
+        if let Some(ref generic_name) = self.generic_name {
+            terms.push((Field::GenericName, Cow::Borrowed(generic_name.as_str())));
+        }
         if let Some(ref comment) = self.comment {
-            terms.push(comment.clone())
+            terms.push((Field::Comment, Cow::Borrowed(comment.as_str())));
         }
-        use std::borrow::{Borrow, Cow};
+        terms.extend(
+            self.categories
+                .iter()
+                .map(|category| (Field::Category, Cow::Borrowed(category.as_str()))),
+        );
+        terms.extend(
+            self.translations
+                .name
+                .iter()
+                .chain(self.translations.generic_name.iter())
+                .chain(self.translations.keywords.iter())
+                .map(|translation| (Field::Translation, Cow::Borrowed(translation.as_str()))),
+        );
         SearchTerms {
-            terms: terms.into_iter().map(Cow::Owned).collect(),
+            terms,
             keywords: self.keywords
                 .iter()
                 .map(Borrow::borrow)
@@ -393,15 +434,63 @@ impl ActionPart {
 }
 
 impl Launch for ActionPart {
-    fn launch(&self, args: Vec<String>) -> Error {
+    fn command_lines(&self, args: Vec<String>) -> Result<Vec<CmdLine>> {
         if !self.application.can_exec() {
-            return ErrorKind::ApplicationNotFound.into();
+            return Err(ErrorKind::ApplicationNotFound.into());
         }
-        info!("Launching '{}'...", self);
 
-        let cmd_line = self.exec.get_command_line(vec![]);
-        let opt = Options { io: Io::Inherit };
-        self.exec(cmd_line, None, &opt)
+        let context = ExecContext {
+            icon: self.icon(),
+            ..self.application.exec_context()
+        };
+        command_lines(
+            self,
+            &self.exec,
+            &context,
+            self.application.terminal,
+            args,
+        )
+    }
+
+    fn log_file(&self) -> Option<PathBuf> {
+        log_file(self)
+    }
+}
+
+fn log_file<D: Details>(details: &D) -> Option<PathBuf> {
+    let id = details.id()?;
+    match log_path(id) {
+        Ok(path) => Some(path),
+        Err(err) => {
+            warn!("Not logging output of '{}': {}", id, err);
+            None
+        }
+    }
+}
+
+fn command_lines<L: Display>(
+    launcher: &L,
+    exec: &Exec,
+    context: &ExecContext,
+    terminal: bool,
+    args: Vec<String>,
+) -> Result<Vec<CmdLine>> {
+    let cmd_lines = if let Some(field_code) = exec.field_code() {
+        let args = field_code.convert_args(args)?;
+        field_code.expand_exec(exec, args, context)?
+    } else if !args.is_empty() {
+        return Err(ErrorKind::ArgsNotAccepted(launcher.to_string()).into());
+    } else {
+        vec![exec.get_command_line(vec![], context)?]
+    };
+    if terminal {
+        let terminal = Terminal::find()?;
+        Ok(cmd_lines
+            .into_iter()
+            .map(|cmd_line| terminal.wrap(cmd_line))
+            .collect())
+    } else {
+        Ok(cmd_lines)
     }
 }
 
@@ -411,10 +500,34 @@ impl Display for ActionPart {
     }
 }
 
+impl Details for ActionPart {
+    fn id(&self) -> Option<&str> {
+        self.application.id()
+    }
+
+    fn icon(&self) -> Option<&str> {
+        self.icon
+            .as_ref()
+            .map(|icon| icon.as_str())
+            .or_else(|| self.application.icon())
+    }
+
+    fn location(&self) -> Option<&Path> {
+        self.application.location()
+    }
+
+    fn terminal(&self) -> bool {
+        self.application.terminal
+    }
+}
+
 impl Search for ActionPart {
     fn search_terms(&self) -> SearchTerms {
-        //        SearchTerms {
-        //        }
-        unimplemented!()
+        use std::borrow::Cow;
+        SearchTerms {
+            terms: vec![(Field::Name, Cow::Borrowed(self.name.as_str()))],
+            keywords: vec![],
+            related: Some(self.application.clone()),
+        }
     }
 }
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use bincode;
+use xdg::BaseDirectories as XdgDirs;
+
+use lunch::errors::*;
+
+use super::desktopfile::DesktopFile;
+use super::locale::Locale;
+
+const CACHE_VERSION: u32 = 1;
+const CACHE_FILE: &str = "index.bin";
+
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct CacheKey {
+    pub version: u32,
+    pub locale: Locale,
+    pub all_translations: bool,
+    pub current_desktops: Vec<String>,
+}
+
+impl CacheKey {
+    pub fn new(locale: &Locale, all_translations: bool, current_desktops: &[String]) -> Self {
+        CacheKey {
+            version: CACHE_VERSION,
+            locale: locale.clone(),
+            all_translations,
+            current_desktops: current_desktops.to_vec(),
+        }
+    }
+}
+
+pub type DirStamp = (PathBuf, Option<SystemTime>);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheData {
+    key: CacheKey,
+    data_dirs: Vec<PathBuf>,
+    dirs: Vec<DirStamp>,
+    found: Vec<(String, PathBuf)>,
+    files: HashMap<PathBuf, (SystemTime, DesktopFile)>,
+}
+
+#[derive(Debug, Default)]
+pub struct Cache {
+    data: CacheData,
+    stale: HashMap<PathBuf, (SystemTime, DesktopFile)>,
+    dirty: bool,
+}
+
+impl Cache {
+    pub fn load(key: CacheKey) -> Cache {
+        let path = XdgDirs::with_prefix("lunch")
+            .ok()
+            .and_then(|xdg| xdg.find_cache_file(CACHE_FILE));
+        match path {
+            Some(path) => Self::load_from(&path, key),
+            None => Self::empty(key),
+        }
+    }
+
+    fn empty(key: CacheKey) -> Cache {
+        Cache {
+            data: CacheData {
+                key,
+                ..CacheData::default()
+            },
+            stale: HashMap::new(),
+            dirty: true,
+        }
+    }
+
+    fn load_from(path: &Path, key: CacheKey) -> Cache {
+        let data: Result<CacheData> = File::open(path)
+            .chain_err(|| "Error opening cache")
+            .and_then(|file| {
+                bincode::deserialize_from(BufReader::new(file))
+                    .chain_err(|| "Error reading cache")
+            });
+        match data {
+            Ok(mut data) => {
+                if data.key != key {
+                    debug!("Cache '{}' is out of date", path.display());
+                    return Self::empty(key);
+                }
+                debug!("Loaded cache '{}'", path.display());
+                let stale = ::std::mem::replace(&mut data.files, HashMap::new());
+                Cache {
+                    data,
+                    stale,
+                    dirty: false,
+                }
+            }
+            Err(err) => {
+                debug!("Not using cache '{}': {}", path.display(), err);
+                Self::empty(key)
+            }
+        }
+    }
+
+    pub fn found(&self, data_dirs: &[PathBuf]) -> Option<Vec<(String, PathBuf)>> {
+        if self.data.data_dirs.as_slice() != data_dirs || self.data.dirs.is_empty() {
+            return None;
+        }
+        let unchanged = self.data
+            .dirs
+            .iter()
+            .all(|&(ref dir, mtime)| modified(dir) == mtime);
+        if unchanged {
+            Some(self.data.found.clone())
+        } else {
+            None
+        }
+    }
+
+    pub fn set_found(
+        &mut self,
+        data_dirs: &[PathBuf],
+        dirs: Vec<DirStamp>,
+        found: &[(String, PathBuf)],
+    ) {
+        self.data.data_dirs = data_dirs.to_vec();
+        self.data.dirs = dirs;
+        self.data.found = found.to_vec();
+        self.dirty = true;
+    }
+
+    pub fn desktop_file(&self, path: &Path, mtime: SystemTime) -> Option<DesktopFile> {
+        match self.stale.get(path) {
+            Some(&(cached_mtime, ref desktop_file)) if cached_mtime == mtime => {
+                trace!("Using cached '{}'", path.display());
+                Some(desktop_file.clone())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn keep(&mut self, path: &Path) {
+        if let Some(cached) = self.stale.remove(path) {
+            self.data.files.insert(path.to_path_buf(), cached);
+        }
+    }
+
+    pub fn insert(&mut self, path: PathBuf, mtime: SystemTime, desktop_file: DesktopFile) {
+        self.data.files.insert(path, (mtime, desktop_file));
+        self.dirty = true;
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if !self.dirty && self.stale.is_empty() {
+            return Ok(());
+        }
+        let path = XdgDirs::with_prefix("lunch")?
+            .place_cache_file(CACHE_FILE)
+            .chain_err(|| "Error creating cache directory")?;
+        self.save_to(&path)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        debug!("Writing cache '{}'", path.display());
+        let tmp_path = path.with_extension("tmp");
+        {
+            let file = File::create(&tmp_path).chain_err(|| "Error creating cache")?;
+            bincode::serialize_into(&mut BufWriter::new(file), &self.data)
+                .chain_err(|| "Error writing cache")?;
+        }
+        fs::rename(&tmp_path, path).chain_err(|| "Error replacing cache")?;
+        Ok(())
+    }
+}
+
+pub fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+    use tempdir::TempDir;
+
+    fn key(locale: &str) -> CacheKey {
+        CacheKey::new(&locale.parse().unwrap(), false, &["GNOME".to_owned()])
+    }
+
+    fn desktop_file() -> DesktopFile {
+        let input = "[Desktop Entry]\nType=Application\nName=App\nExec=app\n";
+        DesktopFile::read(BufReader::new(input.as_bytes()), &"C".parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let tmp_dir = TempDir::new("cache").unwrap();
+        let (cache_path, app_path) = (tmp_dir.path().join(CACHE_FILE), PathBuf::from("app"));
+        let mtime = SystemTime::now();
+        let mut cache = Cache::empty(key("C"));
+        cache.insert(app_path.clone(), mtime, desktop_file());
+        cache.save_to(&cache_path).unwrap();
+
+        let mut cache = Cache::load_from(&cache_path, key("C"));
+        assert_that!(cache.dirty).is_false();
+        assert_that!(cache.desktop_file(&app_path, mtime))
+            .is_some()
+            .is_equal_to(desktop_file());
+        cache.keep(&app_path);
+        assert_that!(cache.stale).is_empty();
+        assert_that!(cache.data.files).contains_key(app_path);
+    }
+
+    #[test]
+    fn changed_mtime() {
+        let mtime = SystemTime::now();
+        let mut cache = Cache::empty(key("C"));
+        cache
+            .stale
+            .insert(PathBuf::from("app"), (mtime, desktop_file()));
+        let later = mtime + ::std::time::Duration::from_secs(1);
+        assert_that!(cache.desktop_file(Path::new("app"), later)).is_none();
+    }
+
+    #[test]
+    fn changed_key() {
+        let tmp_dir = TempDir::new("cache").unwrap();
+        let cache_path = tmp_dir.path().join(CACHE_FILE);
+        let mut cache = Cache::empty(key("C"));
+        cache.insert(PathBuf::from("app"), SystemTime::now(), desktop_file());
+        cache.save_to(&cache_path).unwrap();
+
+        let cache = Cache::load_from(&cache_path, key("de_DE"));
+        assert_that!(cache.stale).is_empty();
+        assert_that!(cache.dirty).is_true();
+    }
+
+    #[test]
+    fn changed_dir() {
+        let tmp_dir = TempDir::new("cache").unwrap();
+        let data_dirs = vec![tmp_dir.path().to_path_buf()];
+        let apps = tmp_dir.path().join("applications");
+        let mut cache = Cache::empty(key("C"));
+        cache.set_found(&data_dirs, vec![(apps.clone(), modified(&apps))], &[]);
+        assert_that!(cache.found(&data_dirs)).is_some();
+
+        fs::create_dir(&apps).unwrap();
+        assert_that!(cache.found(&data_dirs)).is_none();
+    }
+}
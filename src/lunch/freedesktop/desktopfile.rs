@@ -5,22 +5,51 @@ use std::path::PathBuf;
 use lunch::errors::*;
 
 use super::locale::Locale;
-use super::parse::parse_desktop_groups;
+use super::parse::parse_desktop_groups_with_translations;
 use super::entry::*;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub struct DesktopFile {
     pub desktop_entry: DesktopEntry,
     pub actions: Vec<DesktopAction>,
+    pub id: Option<String>,
+    pub path: Option<PathBuf>,
 }
 
 impl DesktopFile {
     pub fn read<R: BufRead>(input: R, locale: &Locale) -> Result<DesktopFile> {
+        Self::read_translated(input, locale, &[])
+    }
+
+    pub fn read_with_translations<R: BufRead>(input: R, locale: &Locale) -> Result<DesktopFile> {
+        Self::read_translated(input, locale, &["Name", "GenericName", "Keywords"])
+    }
+
+    fn read_translated<R: BufRead>(
+        input: R,
+        locale: &Locale,
+        translated_keys: &[&str],
+    ) -> Result<DesktopFile> {
         let input = read_whole(input)?;
-        let mut groups = parse_desktop_groups(&input, locale)?;
-        let desktop_entry = Self::build_desktop_entry(groups
+        let (mut groups, mut translated_groups) =
+            parse_desktop_groups_with_translations(&input, locale, translated_keys)?;
+        let mut desktop_entry = Self::build_desktop_entry(groups
             .remove("Desktop Entry")
             .ok_or(ErrorKind::ApplicationNotFound)?)?;
+        if let Some(mut translations) = translated_groups.remove("Desktop Entry") {
+            desktop_entry.translations = Translations {
+                name: translations.remove("Name").unwrap_or_default(),
+                generic_name: translations.remove("GenericName").unwrap_or_default(),
+                keywords: translations
+                    .remove("Keywords")
+                    .unwrap_or_default()
+                    .iter()
+                    .flat_map(|keywords| keywords.split(';'))
+                    .filter(|val| !val.is_empty())
+                    .map(|keyword| keyword.to_owned())
+                    .collect(),
+            };
+        }
         let actions = groups
             .into_iter()
             .filter(|&(ref key, _)| key.starts_with("Desktop Action "))
@@ -36,6 +65,8 @@ impl DesktopFile {
         Ok(DesktopFile {
             desktop_entry,
             actions,
+            id: None,
+            path: None,
         })
     }
 
@@ -67,6 +98,7 @@ impl DesktopFile {
                 "TryExec" => builder.try_exec(value),
                 "Exec" => builder.exec(value),
                 "Path" => builder.path(PathBuf::from(value)),
+                "Terminal" => builder.terminal(value.parse()?),
                 "Actions" => builder.actions(
                     value
                         .split(';')
@@ -138,6 +170,7 @@ mod read_tests {
         Keywords=word
         Hidden=true
         Path=/
+        Terminal=true
         NoDisplay=false
         OnlyShowIn=A
         NotShowIn=B
@@ -163,10 +196,12 @@ mod read_tests {
                 try_exec: None,
                 exec: Some("exec".to_owned()),
                 path: Some(PathBuf::from("/")),
+                terminal: true,
                 actions: vec!["test".to_owned()],
                 mime_type: vec![],
                 categories: vec!["Utility".to_owned()],
                 keywords: vec!["word".to_owned()],
+                translations: Translations::default(),
             },
             actions: vec![
                 DesktopAction {
@@ -175,9 +210,41 @@ mod read_tests {
                     icon: None,
                 },
             ],
+            id: None,
+            path: None,
         });
     }
 
+    #[test]
+    fn test_translations() {
+        let input = "[Desktop Entry]
+        Name=Files
+        Name[de]=Dateien
+        GenericName=File Manager
+        GenericName[de]=Dateiverwaltung
+        Type=Application
+        Exec=nautilus
+        Keywords=folder;manager;
+        Keywords[de]=Ordner;Verwaltung;
+        ";
+        let locale = "de_DE".parse().unwrap();
+
+        let desktop_file =
+            DesktopFile::read_with_translations(BufReader::new(input.as_bytes()), &locale);
+        assert_that(&desktop_file)
+            .is_ok()
+            .map(|desktop_file| &desktop_file.desktop_entry.name)
+            .is_equal_to("Dateien".to_owned());
+        assert_that(&desktop_file)
+            .is_ok()
+            .map(|desktop_file| &desktop_file.desktop_entry.translations)
+            .is_equal_to(Translations {
+                name: vec!["Files".to_owned()],
+                generic_name: vec!["File Manager".to_owned()],
+                keywords: vec!["folder".to_owned(), "manager".to_owned()],
+            });
+    }
+
     #[test]
     fn test_bad_bool() {
         let input = "[Desktop Entry]
@@ -1,6 +1,6 @@
 use std::path::PathBuf;
 
-#[derive(Debug, Default, Builder, Eq, PartialEq)]
+#[derive(Debug, Default, Clone, Builder, Eq, PartialEq, Serialize, Deserialize)]
 pub struct DesktopEntry {
     #[builder(setter(into))]
     pub entry_type: String,
@@ -26,6 +26,8 @@ pub struct DesktopEntry {
     pub exec: Option<String>,
     #[builder(setter(into), default = "None")]
     pub path: Option<PathBuf>,
+    #[builder(default = "false")]
+    pub terminal: bool,
     #[builder(default = "vec![]")]
     pub actions: Vec<String>,
     #[builder(default = "vec![]")]
@@ -34,9 +36,18 @@ pub struct DesktopEntry {
     pub categories: Vec<String>,
     #[builder(default = "vec![]")]
     pub keywords: Vec<String>,
+    #[builder(default = "Translations::default()")]
+    pub translations: Translations,
+}
+
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Translations {
+    pub name: Vec<String>,
+    pub generic_name: Vec<String>,
+    pub keywords: Vec<String>,
 }
 
-#[derive(Debug, Default, Builder, Eq, PartialEq)]
+#[derive(Debug, Default, Clone, Builder, Eq, PartialEq, Serialize, Deserialize)]
 pub struct DesktopAction {
     #[builder(setter(into))]
     pub name: String,
@@ -1,111 +1,281 @@
-use std::fs::File;
+use std::collections::HashSet;
+use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 use std::io::BufReader;
-use std::borrow::Cow;
 use std::convert::TryFrom;
 
+use rayon::prelude::*;
 use xdg::BaseDirectories as XdgDirs;
 
 use lunch::errors::*;
-use lunch::env::LunchEnv;
+use lunch::env::{InitOptions, LoadReport, LoadStage, LunchEnv};
+use lunch::{Fuzzy, StdResult};
+use lunch::exec::{Exec, ParseMode};
 
+use super::cache::{self, Cache, CacheKey, DirStamp};
 use super::locale::Locale;
 use super::desktopfile::DesktopFile;
+use super::entry::DesktopEntry;
 use super::application::Application;
 
-pub fn init_lunch() -> Result<LunchEnv> {
-    let desktop_files = find_all_desktop_files()?;
+pub fn init_lunch(options: &InitOptions) -> Result<LunchEnv> {
     let locale = Locale::from_env()?;
-    let mut desktop_files = parse_files(desktop_files.into_iter(), &locale);
+    let current_desktops = current_desktops();
+    let mut cache = if options.no_cache {
+        Cache::default()
+    } else {
+        Cache::load(CacheKey::new(
+            &locale,
+            options.all_translations,
+            &current_desktops,
+        ))
+    };
+    let desktop_files = find_all_desktop_files(&mut cache)?;
+    let mut report = LoadReport::default();
+    let mut desktop_files = parse_files(
+        desktop_files.into_iter(),
+        &locale,
+        options,
+        &mut report,
+        &mut cache,
+    );
+    if !options.no_cache {
+        if let Err(err) = cache.save() {
+            warn!("Error saving cache: {}", err);
+        }
+    }
     desktop_files.sort_by_key(|desktop_file| desktop_file.desktop_entry.name.clone());
-    desktop_files.dedup_by_key(|desktop_file| desktop_file.desktop_entry.name.clone());
 
-    let current_desktop = current_desktop()?;
     let desktop_files: Vec<_> = desktop_files
         .into_iter()
         .filter(|desktop_file| !desktop_file.desktop_entry.no_display)
         .filter(|desktop_file| !desktop_file.desktop_entry.hidden)
-        .filter(|desktop_file| {
-            desktop_file.desktop_entry.only_show_in.is_empty()
-                || desktop_file
-                    .desktop_entry
-                    .only_show_in
-                    .iter()
-                    .any(|desktop| desktop == &current_desktop)
-        })
-        .filter(|desktop_file| {
-            desktop_file
-                .desktop_entry
-                .not_show_in
-                .iter()
-                .all(|desktop| desktop != &current_desktop)
-        })
+        .filter(|desktop_file| shown_in(&desktop_file.desktop_entry, &current_desktops))
         .collect();
-    let applications = desktop_files
-        .into_iter()
-        .map(TryFrom::try_from)
-        .collect::<Result<Vec<_>>>()?;
+    let applications = build_applications(desktop_files, &mut report);
     let lunchables = applications
         .into_iter()
         .filter(|application| Application::can_exec(application))
         .flat_map(|application| Application::to_lunchables(application).into_iter())
         .collect();
-    Ok(LunchEnv { lunchables })
+    Ok(LunchEnv {
+        lunchables,
+        fuzzy: Fuzzy::default(),
+        report,
+    })
 }
 
-pub fn current_desktop<'a>() -> Result<Cow<'a, str>> {
-    let xdg_current_desktop = ::std::env::var("XDG_CURRENT_DESKTOP");
-    xdg_current_desktop
-        .map(Cow::Owned)
-        .chain_err(|| ErrorKind::NotDesktopEnvironment)
+fn build_applications(
+    desktop_files: Vec<DesktopFile>,
+    report: &mut LoadReport,
+) -> Vec<Application> {
+    let mut applications = vec![];
+    for desktop_file in desktop_files {
+        let path = desktop_file.path.clone().unwrap_or_default();
+        let strict_errors = strict_exec_errors(&desktop_file);
+        match Application::try_from(desktop_file) {
+            Ok(application) => {
+                for err in strict_errors {
+                    report.load_leniently(path.clone(), err);
+                }
+                applications.push(application);
+            }
+            Err(err) => report.skip(path, LoadStage::Build, err),
+        }
+    }
+    applications
 }
 
-fn find_all_desktop_files() -> Result<Vec<PathBuf>> {
-    let xdg = XdgDirs::new()?;
-    let data_files = xdg.list_data_files_once("applications");
-    let desktop_files = data_files
-        .into_iter()
-        .filter(|path| match path.extension() {
-            Some(os_str) => os_str.to_str() == Some("desktop"),
-            None => false,
+fn strict_exec_errors(desktop_file: &DesktopFile) -> Vec<Error> {
+    desktop_file
+        .desktop_entry
+        .exec
+        .iter()
+        .chain(desktop_file.actions.iter().map(|action| &action.exec))
+        .filter_map(|exec| match Exec::parse_with(exec, ParseMode::Strict) {
+            Err(err @ Error(ErrorKind::InvalidCommandLine(..), _)) => Some(err),
+            _ => None,
         })
-        .map(|path| {
-            trace!("Found desktop file '{}'", path.as_path().display());
-            path
+        .collect()
+}
+
+pub fn current_desktops() -> Vec<String> {
+    match ::std::env::var("XDG_CURRENT_DESKTOP") {
+        Ok(desktops) => parse_desktops(&desktops),
+        Err(_) => {
+            debug!("XDG_CURRENT_DESKTOP not set, hiding OnlyShowIn entries");
+            vec![]
+        }
+    }
+}
+
+fn parse_desktops(desktops: &str) -> Vec<String> {
+    desktops
+        .split(':')
+        .filter(|desktop| !desktop.is_empty())
+        .map(|desktop| desktop.to_owned())
+        .collect()
+}
+
+fn shown_in(desktop_entry: &DesktopEntry, current_desktops: &[String]) -> bool {
+    for desktop in current_desktops {
+        if desktop_entry.only_show_in.contains(desktop) {
+            return true;
+        }
+        if desktop_entry.not_show_in.contains(desktop) {
+            return false;
+        }
+    }
+    desktop_entry.only_show_in.is_empty()
+}
+
+fn find_all_desktop_files(cache: &mut Cache) -> Result<Vec<(String, PathBuf)>> {
+    let xdg = XdgDirs::new()?;
+    let mut data_dirs = vec![xdg.get_data_home()];
+    data_dirs.extend(xdg.get_data_dirs());
+    if let Some(desktop_files) = cache.found(&data_dirs) {
+        debug!("Application directories unchanged, using cached file list");
+        return Ok(desktop_files);
+    }
+    let mut dirs = vec![];
+    let desktop_files = find_desktop_files(&data_dirs, &mut dirs);
+    cache.set_found(&data_dirs, dirs, &desktop_files);
+    Ok(desktop_files)
+}
+
+fn find_desktop_files(data_dirs: &[PathBuf], dirs: &mut Vec<DirStamp>) -> Vec<(String, PathBuf)> {
+    let walked: Vec<_> = data_dirs
+        .par_iter()
+        .map(|data_dir| {
+            let applications = data_dir.join("applications");
+            let (mut paths, dirs) = walk_dir(&applications);
+            paths.sort();
+            (applications, paths, dirs)
         })
         .collect();
-    Ok(desktop_files)
+    let mut seen = HashSet::new();
+    let mut desktop_files = vec![];
+    for (applications, paths, walked_dirs) in walked {
+        dirs.extend(walked_dirs);
+        for path in paths {
+            let id = match desktop_file_id(&applications, &path) {
+                Some(id) => id,
+                None => continue,
+            };
+            if seen.insert(id.clone()) {
+                trace!("Found desktop file '{}' with ID '{}'", path.display(), id);
+                desktop_files.push((id, path));
+            } else {
+                debug!("Desktop file '{}' shadowed by '{}'", path.display(), id);
+            }
+        }
+    }
+    desktop_files
 }
 
-fn parse_files<Iter, T>(desktop_files: Iter, locale: &Locale) -> Vec<DesktopFile>
+fn walk_dir(dir: &Path) -> (Vec<PathBuf>, Vec<DirStamp>) {
+    let mut dirs = vec![(dir.to_path_buf(), cache::modified(dir))];
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            trace!("Skipping '{}': {}", dir.display(), err);
+            return (vec![], dirs);
+        }
+    };
+    let mut paths = vec![];
+    let mut subdirs = vec![];
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let path = entry.path();
+        // symlinked directories are not followed, so a link back up the tree cannot loop
+        if entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false) {
+            subdirs.push(path);
+        } else if path.extension().and_then(|ext| ext.to_str()) == Some("desktop") {
+            paths.push(path);
+        }
+    }
+    let walked: Vec<_> = subdirs.par_iter().map(|subdir| walk_dir(subdir)).collect();
+    for (subdir_paths, subdir_dirs) in walked {
+        paths.extend(subdir_paths);
+        dirs.extend(subdir_dirs);
+    }
+    (paths, dirs)
+}
+
+fn desktop_file_id(applications: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(applications).ok()?;
+    let components = relative
+        .iter()
+        .map(|component| component.to_str())
+        .collect::<Option<Vec<_>>>()?;
+    let id = components.join("-");
+    if id.ends_with(".desktop") {
+        Some(id[..id.len() - ".desktop".len()].to_owned())
+    } else {
+        None
+    }
+}
+
+fn parse_files<Iter>(
+    desktop_files: Iter,
+    locale: &Locale,
+    options: &InitOptions,
+    report: &mut LoadReport,
+    cache: &mut Cache,
+) -> Vec<DesktopFile>
 where
-    Iter: Iterator<Item = T>,
-    T: AsRef<Path>,
+    Iter: Iterator<Item = (String, PathBuf)>,
 {
-    desktop_files
-        .map(|buf| File::open(buf.as_ref()))
-        .filter_map(|file| match file {
-            Ok(e) => {
-                debug!("Opened file {:?}", e);
-                Some(e)
-            }
-            Err(err) => {
-                warn!("Error opening file: {}", err);
-                None
-            }
+    let results: Vec<_> = desktop_files
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|(id, path)| {
+            let mtime = cache::modified(&path);
+            let result = match mtime.and_then(|mtime| cache.desktop_file(&path, mtime)) {
+                Some(desktop_file) => Ok((desktop_file, false)),
+                None => read_file(id, &path, locale, options)
+                    .map(|desktop_file| (desktop_file, true)),
+            };
+            (path, mtime, result)
         })
-        .map(|file| DesktopFile::read(BufReader::new(file), locale))
-        .filter_map(|entry| match entry {
-            Ok(e) => {
-                debug!("Found desktop entry file {:?}", e);
-                Some(e)
-            }
-            Err(err) => {
-                warn!("Error reading desktop file: {}", err);
-                None
+        .collect();
+
+    let mut parsed = vec![];
+    for (path, mtime, result) in results {
+        match result {
+            Ok((desktop_file, fresh)) => {
+                match (fresh, mtime) {
+                    (true, Some(mtime)) => cache.insert(path, mtime, desktop_file.clone()),
+                    (false, _) => cache.keep(&path),
+                    _ => {}
+                }
+                parsed.push(desktop_file);
             }
-        })
-        .collect()
+            Err((stage, err)) => report.skip(path, stage, err),
+        }
+    }
+    parsed
+}
+
+fn read_file(
+    id: String,
+    path: &Path,
+    locale: &Locale,
+    options: &InitOptions,
+) -> StdResult<DesktopFile, (LoadStage, Error)> {
+    let file = File::open(path).map_err(|err| (LoadStage::Open, err.into()))?;
+    debug!("Opened file {:?}", file);
+    let desktop_file = if options.all_translations {
+        DesktopFile::read_with_translations(BufReader::new(file), locale)
+    } else {
+        DesktopFile::read(BufReader::new(file), locale)
+    };
+    let desktop_file = desktop_file.map_err(|err| (LoadStage::Parse, err))?;
+    debug!("Found desktop entry file {:?}", desktop_file);
+    Ok(DesktopFile {
+        id: Some(id),
+        path: Some(path.to_path_buf()),
+        ..desktop_file
+    })
 }
 
 #[cfg(test)]
@@ -148,17 +318,61 @@ mod parse_files_test {
             ).unwrap();
             drop(file);
         }
-        let files = parse_files([path].iter(), &"C".parse().unwrap());
+        let mut report = LoadReport::default();
+        let files = parse_files(
+            vec![("app".to_owned(), path.clone())].into_iter(),
+            &"C".parse().unwrap(),
+            &InitOptions::default(),
+            &mut report,
+            &mut Cache::default(),
+        );
 
         assert_that(&files).has_length(1);
+        assert_that(&report.skipped).is_empty();
+        assert_that(&files[0].id).is_some().is_equal_to("app".to_owned());
+        assert_that(&files[0].path).is_some().is_equal_to(&path);
     }
 
     #[test]
     fn test_err_open() {
         let tmp_dir = TempDir::new("parse_files").unwrap();
         let path = tmp_dir.path().join("non-existent-file");
-        let files = parse_files([path].iter(), &"C".parse().unwrap());
+        let mut report = LoadReport::default();
+        let files = parse_files(
+            vec![("app".to_owned(), path)].into_iter(),
+            &"C".parse().unwrap(),
+            &InitOptions::default(),
+            &mut report,
+            &mut Cache::default(),
+        );
         assert_that(&files).has_length(0);
+        assert_that(&report.skipped).has_length(1);
+        assert_that(&report.skipped[0].stage).is_equal_to(&LoadStage::Open);
+    }
+
+    #[test]
+    fn test_order() {
+        let tmp_dir = TempDir::new("parse_files").unwrap();
+        let desktop_files: Vec<_> = (0..50)
+            .map(|n| {
+                let path = tmp_dir.path().join(format!("app{}.desktop", n));
+                let mut file = File::create(&path).unwrap();
+                writeln!(file, "[Desktop Entry]\nType=Application\nName=App {}\nExec=app", n)
+                    .unwrap();
+                (format!("app{}", n), path)
+            })
+            .collect();
+        let mut report = LoadReport::default();
+        let files = parse_files(
+            desktop_files.clone().into_iter(),
+            &"C".parse().unwrap(),
+            &InitOptions::default(),
+            &mut report,
+            &mut Cache::default(),
+        );
+        let ids: Vec<_> = files.into_iter().filter_map(|file| file.id).collect();
+        let expected: Vec<_> = desktop_files.into_iter().map(|(id, _)| id).collect();
+        assert_that!(ids).is_equal_to(expected);
     }
 
     #[test]
@@ -169,7 +383,140 @@ mod parse_files_test {
             let file = File::create(path.clone()).unwrap();
             drop(file);
         }
-        let files = parse_files([path].iter(), &"C".parse().unwrap());
+        let mut report = LoadReport::default();
+        let files = parse_files(
+            vec![("app".to_owned(), path.clone())].into_iter(),
+            &"C".parse().unwrap(),
+            &InitOptions::default(),
+            &mut report,
+            &mut Cache::default(),
+        );
         assert_that(&files).has_length(0);
+        assert_that(&report.skipped).has_length(1);
+        assert_that(&report.skipped[0].stage).is_equal_to(&LoadStage::Parse);
+        assert_that(&report.skipped[0].path).is_equal_to(&path);
+    }
+}
+
+#[cfg(test)]
+mod find_desktop_files_tests {
+    use super::*;
+    use spectral::prelude::*;
+    use std::fs::{self, File};
+    use tempdir::TempDir;
+
+    fn touch(dir: &Path, name: &str) -> PathBuf {
+        let path = dir.join("applications").join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn subdirectory_ids() {
+        let tmp_dir = TempDir::new("find_desktop_files").unwrap();
+        let data = tmp_dir.path().join("data");
+        let path = touch(&data, "kde4/konsole.desktop");
+        touch(&data, "readme.txt");
+        assert_that!(find_desktop_files(&[data], &mut vec![]))
+            .is_equal_to(vec![("kde4-konsole".to_owned(), path)]);
+    }
+
+    #[test]
+    fn earlier_dir_wins() {
+        let tmp_dir = TempDir::new("find_desktop_files").unwrap();
+        let (home, system) = (tmp_dir.path().join("home"), tmp_dir.path().join("system"));
+        let user = touch(&home, "firefox.desktop");
+        touch(&system, "firefox.desktop");
+        let other = touch(&system, "xterm.desktop");
+        assert_that!(find_desktop_files(&[home, system], &mut vec![])).is_equal_to(vec![
+            ("firefox".to_owned(), user),
+            ("xterm".to_owned(), other),
+        ]);
+    }
+
+    #[test]
+    fn symlink_loop() {
+        let tmp_dir = TempDir::new("find_desktop_files").unwrap();
+        let data = tmp_dir.path().join("data");
+        let path = touch(&data, "xterm.desktop");
+        ::std::os::unix::fs::symlink("..", data.join("applications/loop")).unwrap();
+        assert_that!(find_desktop_files(&[data], &mut vec![]))
+            .is_equal_to(vec![("xterm".to_owned(), path)]);
+    }
+
+    #[test]
+    fn missing_dir() {
+        let tmp_dir = TempDir::new("find_desktop_files").unwrap();
+        assert_that!(find_desktop_files(&[tmp_dir.path().join("missing")], &mut vec![])).is_empty();
+    }
+}
+
+#[cfg(test)]
+mod shown_in_tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn entry(only_show_in: &[&str], not_show_in: &[&str]) -> DesktopEntry {
+        DesktopEntry {
+            only_show_in: only_show_in.iter().map(|s| (*s).to_owned()).collect(),
+            not_show_in: not_show_in.iter().map(|s| (*s).to_owned()).collect(),
+            ..DesktopEntry::default()
+        }
+    }
+
+    #[test]
+    fn colon_separated() {
+        assert_that!(parse_desktops("ubuntu:GNOME"))
+            .is_equal_to(vec!["ubuntu".to_owned(), "GNOME".to_owned()]);
+        assert_that!(parse_desktops("")).is_empty();
+    }
+
+    #[test]
+    fn any_desktop_matches() {
+        let desktops = parse_desktops("ubuntu:GNOME");
+        assert_that!(shown_in(&entry(&["GNOME"], &[]), &desktops)).is_true();
+        assert_that!(shown_in(&entry(&["KDE"], &[]), &desktops)).is_false();
+        assert_that!(shown_in(&entry(&[], &["GNOME"]), &desktops)).is_false();
+        assert_that!(shown_in(&entry(&[], &["KDE"]), &desktops)).is_true();
+    }
+
+    #[test]
+    fn first_listed_desktop_wins() {
+        let desktops = parse_desktops("ubuntu:GNOME");
+        assert_that!(shown_in(&entry(&["GNOME"], &["ubuntu"]), &desktops)).is_false();
+        assert_that!(shown_in(&entry(&["ubuntu"], &["GNOME"]), &desktops)).is_true();
+    }
+
+    #[test]
+    fn unknown_desktop() {
+        assert_that!(shown_in(&entry(&[], &[]), &[])).is_true();
+        assert_that!(shown_in(&entry(&[], &["GNOME"]), &[])).is_true();
+        assert_that!(shown_in(&entry(&["GNOME"], &[]), &[])).is_false();
+    }
+}
+
+#[cfg(test)]
+mod build_applications_tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    fn desktop_file(exec: &str) -> DesktopFile {
+        let input = format!("[Desktop Entry]\nType=Application\nName=App\nExec={}\n", exec);
+        let mut desktop_file =
+            DesktopFile::read(BufReader::new(input.as_bytes()), &"C".parse().unwrap()).unwrap();
+        desktop_file.path = Some(PathBuf::from("app.desktop"));
+        desktop_file
+    }
+
+    #[test]
+    fn lenient_exec_reported() {
+        let mut report = LoadReport::default();
+        let desktop_files = vec![desktop_file("sh -c 'echo hi'"), desktop_file("app %f")];
+        assert_that!(build_applications(desktop_files, &mut report)).has_length(2);
+        assert_that(&report.skipped).is_empty();
+        assert_that(&report.loaded_leniently).has_length(1);
+        assert_that(&report.loaded_leniently[0].stage).is_equal_to(&LoadStage::Parse);
+        assert_that(&report.loaded_leniently[0].path).is_equal_to(&PathBuf::from("app.desktop"));
     }
 }
@@ -2,7 +2,7 @@ use std::str::FromStr;
 use lunch::StdResult;
 use lunch::errors::*;
 
-#[derive(Debug, Default, Eq, PartialEq, Hash, Clone)]
+#[derive(Debug, Default, Eq, PartialEq, Hash, Clone, Serialize, Deserialize)]
 pub struct Locale {
     lang: String,
     country: Option<String>,
@@ -1,6 +1,8 @@
 pub mod locale;
 mod parse;
 mod application;
+mod terminal;
+mod cache;
 pub mod entry;
 pub mod desktopfile;
 pub mod env;
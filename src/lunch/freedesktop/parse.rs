@@ -8,20 +8,41 @@ use super::locale::Locale;
 
 type Group = HashMap<String, String>;
 type Groups = HashMap<String, Group>;
+type TranslatedValues = HashMap<String, Vec<String>>;
+type TranslatedGroups = HashMap<String, TranslatedValues>;
 
 pub fn parse_desktop_groups(src: &str, locale: &Locale) -> Result<Groups> {
+    parse_desktop_groups_with_translations(src, locale, &[]).map(|(groups, _)| groups)
+}
+
+pub fn parse_desktop_groups_with_translations(
+    src: &str,
+    locale: &Locale,
+    translated_keys: &[&str],
+) -> Result<(Groups, TranslatedGroups)> {
     let mut groups = Groups::new();
+    let mut translated_groups = TranslatedGroups::new();
     let mut lines = src.lines().peekable();
     while lines.peek().is_some() {
         if let Some((header, localised_group)) = parse_localised_desktop_group(&mut lines) {
+            let mut translations = localised_group.translations(translated_keys);
             let group = localised_group.resolve_to_locale(locale);
+            for (key, values) in &mut translations {
+                if let Some(value) = group.get(key) {
+                    values.retain(|translation| translation != value);
+                }
+            }
+            translations.retain(|_, values| !values.is_empty());
+            if !translations.is_empty() {
+                translated_groups.insert(header.clone(), translations);
+            }
             groups.insert(header, group);
         }
     }
     if groups.is_empty() {
         Err(ErrorKind::NoGroupsFound.into())
     } else {
-        Ok(groups)
+        Ok((groups, translated_groups))
     }
 }
 
@@ -59,6 +80,33 @@ mod parse_desktop_groups_tests {
             }
         });
     }
+
+    #[test]
+    fn parse_desktop_groups_translations() {
+        let input = "[Desktop Entry]
+        Name=Files
+        Name[de]=Dateien
+        Name[fr]=Fichiers
+        Comment=Access and organize files
+        Comment[de]=Auf Dateien zugreifen
+        ";
+        let (groups, translations) = parse_desktop_groups_with_translations(
+            input,
+            &"de_DE".parse().unwrap(),
+            &["Name"],
+        ).unwrap();
+        assert_that!(groups).is_equal_to(hashmap!{
+            "Desktop Entry".to_owned() => hashmap!{
+                "Name".to_owned() => "Dateien".to_owned(),
+                "Comment".to_owned() => "Auf Dateien zugreifen".to_owned(),
+            }
+        });
+        assert_that!(translations).is_equal_to(hashmap!{
+            "Desktop Entry".to_owned() => hashmap!{
+                "Name".to_owned() => vec!["Files".to_owned(), "Fichiers".to_owned()],
+            }
+        });
+    }
 }
 
 #[derive(Debug, Default, Eq, PartialEq)]
@@ -81,6 +129,23 @@ impl LocalisedGroup {
             .filter_map(|(key, value)| value.map(|value| (key, value)))
             .collect()
     }
+
+    fn translations(&self, keys: &[&str]) -> TranslatedValues {
+        keys.iter()
+            .filter_map(|key| {
+                self.group.get(*key).map(|localised_value| {
+                    (
+                        key.to_string(),
+                        localised_value
+                            .localised_value
+                            .iter()
+                            .map(|&(_, ref value)| value.clone())
+                            .collect(),
+                    )
+                })
+            })
+            .collect()
+    }
 }
 
 #[cfg(test)]
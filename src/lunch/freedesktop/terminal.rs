@@ -0,0 +1,187 @@
+use std::ffi::OsString;
+use std::path::Path;
+
+use lunch::errors::*;
+use lunch::exec::CmdLine;
+
+use super::application::can_exec;
+
+const TERMINALS: &[(&str, &[&str])] = &[
+    ("x-terminal-emulator", &["-e"]),
+    ("gnome-terminal", &["--"]),
+    ("kgx", &["--"]),
+    ("konsole", &["-e"]),
+    ("xfce4-terminal", &["-x"]),
+    ("mate-terminal", &["-x"]),
+    ("lxterminal", &["-e"]),
+    ("qterminal", &["-e"]),
+    ("terminator", &["-x"]),
+    ("tilix", &["-e"]),
+    ("alacritty", &["-e"]),
+    ("kitty", &[]),
+    ("foot", &[]),
+    ("wezterm", &["start", "--"]),
+    ("urxvt", &["-e"]),
+    ("st", &["-e"]),
+    ("xterm", &["-e"]),
+];
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Terminal {
+    cmd: String,
+    args: Vec<String>,
+}
+
+impl Terminal {
+    pub fn find() -> Result<Terminal> {
+        Self::find_with(
+            ::std::env::var("LUNCH_TERMINAL").ok(),
+            ::std::env::var("TERMINAL").ok(),
+            ::std::env::var_os("PATH"),
+        ).ok_or_else(|| ErrorKind::TerminalNotFound.into())
+    }
+
+    fn find_with(
+        user_terminal: Option<String>,
+        env_terminal: Option<String>,
+        env_path: Option<OsString>,
+    ) -> Option<Terminal> {
+        if let Some(terminal) = user_terminal.and_then(|terminal| Self::from_setting(&terminal)) {
+            debug!("Using configured terminal {:?}", terminal);
+            return Some(terminal);
+        }
+        if can_exec(Path::new("xdg-terminal-exec"), env_path.clone()) {
+            debug!("Using xdg-terminal-exec");
+            return Some(Terminal {
+                cmd: "xdg-terminal-exec".to_owned(),
+                args: vec![],
+            });
+        }
+        if let Some(terminal) = env_terminal.and_then(|terminal| Self::from_setting(&terminal)) {
+            debug!("Using $TERMINAL {:?}", terminal);
+            return Some(terminal);
+        }
+        TERMINALS
+            .iter()
+            .find(|&&(cmd, _)| can_exec(Path::new(cmd), env_path.clone()))
+            .map(|&(cmd, args)| Terminal {
+                cmd: cmd.to_owned(),
+                args: args.iter().map(|arg| (*arg).to_owned()).collect(),
+            })
+    }
+
+    fn from_setting(setting: &str) -> Option<Terminal> {
+        let mut words = setting.split_whitespace().map(|word| word.to_owned());
+        let cmd = words.next()?;
+        let mut args: Vec<String> = words.collect();
+        if args.is_empty() {
+            args = Path::new(&cmd)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| TERMINALS.iter().find(|&&(known, _)| known == name))
+                .map(|&(_, args)| args.iter().map(|arg| (*arg).to_owned()).collect())
+                .unwrap_or_else(|| vec!["-e".to_owned()]);
+        }
+        Some(Terminal { cmd, args })
+    }
+
+    pub fn wrap(&self, cmd_line: CmdLine) -> CmdLine {
+        let mut args = self.args.clone();
+        args.push(cmd_line.cmd);
+        args.extend(cmd_line.args);
+        CmdLine {
+            cmd: self.cmd.clone(),
+            args,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+    use std::fs::File;
+    use std::os::unix::fs::PermissionsExt;
+    use tempdir::TempDir;
+
+    fn path_with(cmds: &[&str]) -> (TempDir, OsString) {
+        let tmp_dir = TempDir::new("terminal").unwrap();
+        for cmd in cmds {
+            let file = File::create(tmp_dir.path().join(cmd)).unwrap();
+            file.set_permissions(PermissionsExt::from_mode(0o755))
+                .unwrap();
+        }
+        let path = tmp_dir.path().as_os_str().to_owned();
+        (tmp_dir, path)
+    }
+
+    fn terminal(cmd: &str, args: &[&str]) -> Terminal {
+        Terminal {
+            cmd: cmd.to_owned(),
+            args: args.iter().map(|arg| (*arg).to_owned()).collect(),
+        }
+    }
+
+    #[test]
+    fn user_setting_first() {
+        let (_tmp_dir, path) = path_with(&["xdg-terminal-exec", "xterm"]);
+        let found = Terminal::find_with(Some("kitty".to_owned()), None, Some(path));
+        assert_that!(found).is_some().is_equal_to(terminal("kitty", &[]));
+    }
+
+    #[test]
+    fn user_setting_with_args() {
+        let found = Terminal::find_with(Some("alacritty --class lunch -e".to_owned()), None, None);
+        assert_that!(found)
+            .is_some()
+            .is_equal_to(terminal("alacritty", &["--class", "lunch", "-e"]));
+    }
+
+    #[test]
+    fn unknown_terminal_uses_dash_e() {
+        let found = Terminal::find_with(None, Some("/opt/myterm".to_owned()), None);
+        assert_that!(found)
+            .is_some()
+            .is_equal_to(terminal("/opt/myterm", &["-e"]));
+    }
+
+    #[test]
+    fn xdg_terminal_exec_before_env() {
+        let (_tmp_dir, path) = path_with(&["xdg-terminal-exec"]);
+        let found = Terminal::find_with(None, Some("xterm".to_owned()), Some(path));
+        assert_that!(found)
+            .is_some()
+            .is_equal_to(terminal("xdg-terminal-exec", &[]));
+    }
+
+    #[test]
+    fn fallback_list() {
+        let (_tmp_dir, path) = path_with(&["xterm", "gnome-terminal"]);
+        let found = Terminal::find_with(None, None, Some(path));
+        assert_that!(found)
+            .is_some()
+            .is_equal_to(terminal("gnome-terminal", &["--"]));
+    }
+
+    #[test]
+    fn not_found() {
+        let (_tmp_dir, path) = path_with(&[]);
+        assert_that!(Terminal::find_with(None, None, Some(path))).is_none();
+    }
+
+    #[test]
+    fn wrap() {
+        let cmd_line = terminal("wezterm", &["start", "--"]).wrap(CmdLine {
+            cmd: "htop".to_owned(),
+            args: vec!["-d".to_owned(), "10".to_owned()],
+        });
+        assert_that!(cmd_line.cmd).is_equal_to("wezterm".to_owned());
+        assert_that!(cmd_line.args).is_equal_to(vec![
+            "start".to_owned(),
+            "--".to_owned(),
+            "htop".to_owned(),
+            "-d".to_owned(),
+            "10".to_owned(),
+        ]);
+    }
+}
@@ -0,0 +1,168 @@
+use std::cmp::{max, min};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Fuzzy {
+    pub threshold: f32,
+}
+
+impl Default for Fuzzy {
+    fn default() -> Self {
+        Fuzzy { threshold: 0.75 }
+    }
+}
+
+impl Fuzzy {
+    pub fn new(threshold: f32) -> Self {
+        Fuzzy { threshold }
+    }
+
+    pub fn similarity(&self, query: &str, word: &str) -> Option<f32> {
+        let len = max(query.chars().count(), word.chars().count());
+        if len == 0 {
+            return None;
+        }
+        let similarity = 1.0 - edit_distance(query, word) as f32 / len as f32;
+        if similarity >= self.threshold {
+            Some(similarity)
+        } else {
+            None
+        }
+    }
+
+    pub fn prefix_similarity(&self, query: &str, word: &str) -> Option<f32> {
+        let prefix: String = word.chars().take(query.chars().count()).collect();
+        self.similarity(query, &prefix)
+    }
+}
+
+#[cfg(test)]
+mod fuzzy_tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn similarity_within_threshold() {
+        assert_that!(Fuzzy::default().similarity("fierfox", "firefox")).is_some();
+        assert_that!(Fuzzy::default().similarity("thunderbrid", "thunderbird")).is_some();
+    }
+
+    #[test]
+    fn similarity_outside_threshold() {
+        assert_that!(Fuzzy::default().similarity("vim", "gimp")).is_none();
+    }
+
+    #[test]
+    fn custom_threshold() {
+        assert_that!(Fuzzy::new(0.9).similarity("fierfox", "firefox")).is_none();
+        assert_that!(Fuzzy::new(0.5).similarity("vim", "gimp")).is_some();
+    }
+
+    #[test]
+    fn prefix_similarity() {
+        assert_that!(Fuzzy::default().prefix_similarity("thudner", "thunderbird")).is_some();
+        assert_that!(Fuzzy::default().similarity("thudner", "thunderbird")).is_none();
+    }
+}
+
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for j in 0..b.len() + 1 {
+        d[0][j] = j;
+    }
+    for i in 1..a.len() + 1 {
+        for j in 1..b.len() + 1 {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = min(min(d[i - 1][j] + 1, d[i][j - 1] + 1), d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = min(d[i][j], d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod edit_distance_tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn equal() {
+        assert_that!(edit_distance("firefox", "firefox")).is_equal_to(0);
+    }
+
+    #[test]
+    fn empty() {
+        assert_that!(edit_distance("", "firefox")).is_equal_to(7);
+        assert_that!(edit_distance("firefox", "")).is_equal_to(7);
+    }
+
+    #[test]
+    fn substitution_insertion_deletion() {
+        assert_that!(edit_distance("firefix", "firefox")).is_equal_to(1);
+        assert_that!(edit_distance("firefoxx", "firefox")).is_equal_to(1);
+        assert_that!(edit_distance("firfox", "firefox")).is_equal_to(1);
+    }
+
+    #[test]
+    fn transposition() {
+        assert_that!(edit_distance("fierfox", "firefox")).is_equal_to(1);
+        assert_that!(edit_distance("thunderbrid", "thunderbird")).is_equal_to(1);
+    }
+}
+
+pub fn subsequence(query: &str, word: &str) -> Option<f32> {
+    let query: Vec<char> = query.chars().collect();
+    if query.is_empty() || word.chars().next() != Some(query[0]) {
+        return None;
+    }
+    let mut matched = 0;
+    let mut span = 0;
+    for (i, c) in word.chars().enumerate() {
+        if c == query[matched] {
+            matched += 1;
+            if matched == query.len() {
+                span = i + 1;
+                break;
+            }
+        }
+    }
+    if matched == query.len() {
+        Some(query.len() as f32 / span as f32)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod subsequence_tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn match_compact() {
+        assert_that!(subsequence("fox", "fox")).is_some().is_equal_to(1.0);
+    }
+
+    #[test]
+    fn match_spread() {
+        assert_that!(subsequence("ffx", "firefox"))
+            .is_some()
+            .is_equal_to(3.0 / 7.0);
+    }
+
+    #[test]
+    fn first_char_must_match() {
+        assert_that!(subsequence("rfx", "firefox")).is_none();
+    }
+
+    #[test]
+    fn out_of_order() {
+        assert_that!(subsequence("fxo", "firefox")).is_none();
+    }
+}
@@ -1,9 +1,12 @@
 use std::borrow::Borrow;
 
-use super::search::Search;
+use super::fuzzy::Fuzzy;
+use super::normalise::normalise;
+use super::search::{Field, Match, Search};
 
 pub struct Keyword<T, S: ?Sized> {
     search_items: Vec<T>,
+    fuzzy: Fuzzy,
     phantom: ::std::marker::PhantomData<*mut S>,
 }
 
@@ -15,84 +18,64 @@ where
     pub fn new(search_items: Vec<T>) -> Self {
         Keyword {
             search_items,
+            fuzzy: Fuzzy::default(),
             phantom: ::std::marker::PhantomData,
         }
     }
 
-    pub fn search(mut self, keyword: &str) -> Option<T> {
-        if let Some(n) = self.find(|ref search_item: &T| {
-            let search_item: &T = search_item;
-            search_item
-                .borrow()
-                .search_terms()
-                .keywords
-                .iter()
-                .any(|k| k == keyword)
-        }) {
-            return Some(self.search_items.swap_remove(n));
-        }
-        if keyword.len() > 3 {
-            if let Some(n) = self.find(|ref search_item| {
-                let search_item: &T = search_item;
-                search_item
-                    .borrow()
-                    .search_terms()
-                    .keywords
-                    .iter()
-                    .any(|k| k.starts_with(keyword))
-            }) {
-                return Some(self.search_items.swap_remove(n));
-            }
-        }
-        None
+    pub fn fuzzy(mut self, fuzzy: Fuzzy) -> Self {
+        self.fuzzy = fuzzy;
+        self
     }
 
-    fn find<P>(&self, mut predicate: P) -> Option<usize>
-    where
-        P: FnMut(&T) -> bool,
-    {
-        self.search_items
-            .iter()
-            .enumerate()
-            .filter(|&(_, ref search_item)| predicate(search_item.borrow()))
-            .map(|(n, _)| n)
-            .next()
+    pub fn search(self, keyword: &str) -> Option<T> {
+        self.matches(keyword).into_iter().next().map(|m| m.item)
+    }
+
+    pub fn matches(self, keyword: &str) -> Vec<Match<T>> {
+        let keyword = normalise(keyword);
+        let fuzzy = self.fuzzy;
+        let mut matches: Vec<Match<T>> = self.search_items
+            .into_iter()
+            .filter_map(|search_item| {
+                let score = score(&keyword, &search_item.borrow().search_terms().keywords, &fuzzy);
+                score.map(|score| Match {
+                    item: search_item,
+                    score,
+                    fields: vec![Field::Keyword],
+                })
+            })
+            .collect();
+        matches.sort_by(|a, b| b.score.cmp(&a.score));
+        matches
     }
 }
 
+fn score<K: AsRef<str>>(keyword: &str, keywords: &[K], fuzzy: &Fuzzy) -> Option<u32> {
+    let keyword_len = keyword.chars().count();
+    keywords
+        .iter()
+        .map(|k| normalise(k.as_ref()))
+        .filter_map(|k| {
+            if k == keyword {
+                Some(100)
+            } else if keyword.len() > 3 && k.starts_with(keyword) {
+                Some(50 + (50 * keyword_len / k.chars().count()) as u32)
+            } else {
+                fuzzy
+                    .similarity(keyword, &k)
+                    .map(|similarity| (45.0 * similarity) as u32)
+            }
+        })
+        .max()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use spectral::prelude::*;
     use std::borrow::Cow;
-    use lunch::search::SearchTerms;
-
-    #[derive(Debug)]
-    struct DummySearch<'a> {
-        search_terms: SearchTerms<'a>,
-    }
-
-    impl<'a> DummySearch<'a> {
-        fn new<'b: 'a>(terms: Vec<&'b str>, keywords: Vec<&'b str>) -> Self {
-            DummySearch {
-                search_terms: SearchTerms {
-                    terms: terms.iter().map(|s| Cow::Borrowed(*s)).collect(),
-                    keywords: keywords.iter().map(|s| Cow::Borrowed(*s)).collect(),
-                    related: None,
-                },
-            }
-        }
-    }
-
-    impl<'a> Search for DummySearch<'a> {
-        fn search_terms<'b>(&'b self) -> SearchTerms<'b> {
-            SearchTerms {
-                terms: self.search_terms.terms.clone(),
-                keywords: self.search_terms.keywords.clone(),
-                related: None,
-            }
-        }
-    }
+    use lunch::test_support::DummySearch;
 
     #[test]
     fn keyword_match() {
@@ -116,11 +99,20 @@ mod tests {
     fn keyword_no_match() {
         let keyword_searcher = Keyword::<Box<DummySearch>, DummySearch>::new(vec![
             Box::new(DummySearch::new(vec![], vec!["keyword"])),
-        ]);
+        ]).fuzzy(Fuzzy::new(1.0));
 
         assert_that!(keyword_searcher.search("keyword1")).is_none();
     }
 
+    #[test]
+    fn keyword_no_fuzzy_match() {
+        let keyword_searcher = Keyword::<Box<DummySearch>, DummySearch>::new(vec![
+            Box::new(DummySearch::new(vec![], vec!["keyword"])),
+        ]);
+
+        assert_that!(keyword_searcher.search("password")).is_none();
+    }
+
     #[test]
     fn keyword_too_short() {
         let keyword_searcher = Keyword::<Box<DummySearch>, DummySearch>::new(vec![
@@ -129,4 +121,51 @@ mod tests {
 
         assert_that!(keyword_searcher.search("ke")).is_none();
     }
+
+    #[test]
+    fn keyword_typo() {
+        let keyword_searcher = Keyword::<Box<DummySearch>, DummySearch>::new(vec![
+            Box::new(DummySearch::new(vec![], vec!["thunderbird"])),
+            Box::new(DummySearch::new(vec![], vec!["firefox"])),
+        ]);
+
+        assert_that!(keyword_searcher.search("fierfox"))
+            .is_some()
+            .map(|search| &search.search_terms.keywords[0])
+            .is_equal_to(Cow::Borrowed("firefox"));
+    }
+
+    #[test]
+    fn keyword_typo_threshold() {
+        let keyword_searcher = Keyword::<Box<DummySearch>, DummySearch>::new(vec![
+            Box::new(DummySearch::new(vec![], vec!["thunderbird"])),
+        ]).fuzzy(Fuzzy::new(0.95));
+
+        assert_that!(keyword_searcher.search("thunderbrid")).is_none();
+    }
+
+    #[test]
+    fn keyword_normalised() {
+        let keyword_searcher = Keyword::<Box<DummySearch>, DummySearch>::new(vec![
+            Box::new(DummySearch::new(vec![], vec!["Écrire"])),
+        ]);
+
+        assert_that!(keyword_searcher.search("ECRIRE")).is_some();
+    }
+
+    #[test]
+    fn keyword_all_matches() {
+        let keyword_searcher = Keyword::<Box<DummySearch>, DummySearch>::new(vec![
+            Box::new(DummySearch::new(vec![], vec!["keywords"])),
+            Box::new(DummySearch::new(vec![], vec!["keyword"])),
+            Box::new(DummySearch::new(vec![], vec!["other"])),
+            Box::new(DummySearch::new(vec![], vec!["keyword"])),
+        ]);
+
+        let matches = keyword_searcher.matches("keyword");
+        assert_that!(matches).has_length(3);
+        assert_that!(matches[0].score).is_equal_to(100);
+        assert_that!(matches[1].score).is_equal_to(100);
+        assert_that!(matches[2].score).is_less_than(100);
+    }
 }
@@ -1,33 +1,531 @@
-use std::process::{Child, Command, Stdio};
-use std::path::Path;
+use std::fs::File;
+use std::io;
+use std::mem;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::atomic::{AtomicIsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
 
 #[cfg(unix)]
-use std::os::unix::process::CommandExt;
+use std::os::unix::io::FromRawFd;
+#[cfg(unix)]
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+
+#[cfg(unix)]
+use libc::{self, c_int, c_uint, c_void, pid_t};
 
 use super::errors::*;
 use super::exec::CmdLine;
+use super::logs::LogRelay;
 use lunch::{Io, Options};
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LaunchMode {
+    Exec,
+    Detach,
+    Wait,
+}
+
+#[derive(Debug)]
+pub enum LaunchOutcome {
+    Spawned(Vec<u32>),
+    Exited(Vec<ExitStatus>),
+    Exec(Vec<u32>, ExecCommand),
+}
+
+#[derive(Debug)]
+pub struct ExecCommand {
+    cmd_line: CmdLine,
+    cmd: Command,
+}
+
+impl ExecCommand {
+    pub fn cmd_line(&self) -> &CmdLine {
+        &self.cmd_line
+    }
+
+    #[cfg(unix)]
+    pub fn exec(mut self) -> Error {
+        debug!("execing {:?}", self.cmd_line);
+        self.cmd.exec().into()
+    }
+}
+
+impl LaunchOutcome {
+    #[cfg(unix)]
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            LaunchOutcome::Spawned(_) | LaunchOutcome::Exec(..) => 0,
+            LaunchOutcome::Exited(ref statuses) => statuses
+                .iter()
+                .map(|status| match (status.code(), status.signal()) {
+                    (Some(code), _) => code,
+                    (None, Some(signal)) => 128 + signal,
+                    (None, None) => 1,
+                })
+                .find(|code| *code != 0)
+                .unwrap_or(0),
+        }
+    }
+}
+
 pub trait Launch {
-    fn launch(&self, args: Vec<String>) -> Error;
+    fn command_lines(&self, args: Vec<String>) -> Result<Vec<CmdLine>>;
+
+    fn work_dir(&self) -> Option<&Path> {
+        None
+    }
+
+    fn log_file(&self) -> Option<PathBuf> {
+        None
+    }
+
+    fn launch(&self, args: Vec<String>, mode: LaunchMode) -> Result<LaunchOutcome> {
+        let mut cmd_lines = self.command_lines(args)?;
+        let work_dir = self.work_dir();
+        match mode {
+            LaunchMode::Exec => {
+                let last = match cmd_lines.pop() {
+                    Some(cmd_line) => cmd_line,
+                    None => return Ok(LaunchOutcome::Spawned(vec![])),
+                };
+                let pids = cmd_lines
+                    .into_iter()
+                    .map(|cmd_line| {
+                        self.spawn_detached(cmd_line, work_dir, &Options { io: Io::Suppress })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let cmd = init_cmd(&last, work_dir, &Options { io: Io::Inherit });
+                Ok(LaunchOutcome::Exec(pids, ExecCommand { cmd_line: last, cmd }))
+            }
+            LaunchMode::Detach => {
+                let opt = match self.log_file() {
+                    Some(path) => Options { io: Io::Log(path) },
+                    None => Options { io: Io::Suppress },
+                };
+                let pids = cmd_lines
+                    .into_iter()
+                    .map(|cmd_line| self.spawn_detached(cmd_line, work_dir, &opt))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(LaunchOutcome::Spawned(pids))
+            }
+            LaunchMode::Wait => {
+                let mut forwarder = SignalForwarder::new()?;
+                let mut children = Vec::with_capacity(cmd_lines.len());
+                for cmd_line in cmd_lines {
+                    match self.spawn(cmd_line, work_dir, &Options { io: Io::Inherit }) {
+                        Ok(child) => children.push(child),
+                        Err(err) => {
+                            for mut child in children {
+                                debug!("killing {} after failed spawn", child.id());
+                                let _ = child.kill();
+                                let _ = child.wait();
+                            }
+                            return Err(err);
+                        }
+                    }
+                }
+                forwarder.forward(children.iter().map(|child| child.id()).collect());
+                let statuses = children
+                    .into_iter()
+                    .map(|mut child| child.wait().chain_err(|| "Error waiting for process"))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(LaunchOutcome::Exited(statuses))
+            }
+        }
+    }
 
     fn spawn(&self, cmd_line: CmdLine, work_dir: Option<&Path>, opt: &Options) -> Result<Child> {
         debug!("spawning {:?}", cmd_line);
-        let mut cmd = init_cmd(cmd_line, work_dir, opt);
+        let mut cmd = init_cmd(&cmd_line, work_dir, opt);
         cmd.spawn().chain_err(|| "Error spawning process")
     }
 
     #[cfg(unix)]
-    fn exec(&self, cmd_line: CmdLine, work_dir: Option<&Path>, opt: &Options) -> Error {
-        debug!("execing {:?}", cmd_line);
-        let mut cmd = init_cmd(cmd_line, work_dir, opt);
-        cmd.exec().into()
+    fn spawn_detached(
+        &self,
+        cmd_line: CmdLine,
+        work_dir: Option<&Path>,
+        opt: &Options,
+    ) -> Result<u32> {
+        debug!("spawning detached {:?}", cmd_line);
+        let cmd = init_cmd(&cmd_line, work_dir, opt);
+        spawn_detached(cmd)
     }
 }
 
-fn init_cmd(cmd_line: CmdLine, work_dir: Option<&Path>, opt: &Options) -> Command {
-    let mut cmd = Command::new(cmd_line.cmd);
-    cmd.args(cmd_line.args);
+#[cfg(unix)]
+const MAX_SIGNAL: c_int = 64;
+
+#[cfg(unix)]
+fn spawn_detached(mut cmd: Command) -> Result<u32> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error()).chain_err(|| "Error creating pipe");
+    }
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+    unsafe {
+        libc::fcntl(read_fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        libc::fcntl(write_fd, libc::F_SETFD, libc::FD_CLOEXEC);
+    }
+    cmd.before_exec(move || detach(write_fd));
+    let spawned = cmd.spawn();
+    unsafe {
+        libc::close(write_fd);
+    }
+    let result = spawned
+        .chain_err(|| "Error spawning process")
+        .and_then(|mut child| {
+            child
+                .wait()
+                .chain_err(|| "Error waiting for intermediate process")
+        })
+        .and_then(|_| {
+            let mut pid: pid_t = 0;
+            let len = unsafe {
+                libc::read(
+                    read_fd,
+                    &mut pid as *mut pid_t as *mut c_void,
+                    mem::size_of::<pid_t>(),
+                )
+            };
+            if len == mem::size_of::<pid_t>() as isize {
+                Ok(pid as u32)
+            } else {
+                Err("Error reading detached process id".into())
+            }
+        });
+    unsafe {
+        libc::close(read_fd);
+    }
+    result
+}
+
+#[cfg(unix)]
+fn relay_log(path: &Path, cmd_line: &CmdLine) -> Result<File> {
+    let relay = LogRelay::new(path)?;
+    relay.write_header(cmd_line)?;
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(io::Error::last_os_error()).chain_err(|| "Error creating log pipe");
+    }
+    let (read_fd, write_fd) = (fds[0], fds[1]);
+    unsafe {
+        libc::fcntl(read_fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        libc::fcntl(write_fd, libc::F_SETFD, libc::FD_CLOEXEC);
+    }
+    let spawned = spawn_relay(&relay, read_fd);
+    unsafe {
+        libc::close(read_fd);
+    }
+    match spawned {
+        Ok(()) => {
+            debug!("relaying output to '{}'", path.display());
+            Ok(unsafe { File::from_raw_fd(write_fd) })
+        }
+        Err(err) => {
+            unsafe {
+                libc::close(write_fd);
+            }
+            Err(err)
+        }
+    }
+}
+
+#[cfg(unix)]
+fn spawn_relay(relay: &LogRelay, input: c_int) -> Result<()> {
+    let pid = unsafe { libc::fork() };
+    if pid < 0 {
+        return Err(io::Error::last_os_error()).chain_err(|| "Error forking log relay");
+    }
+    if pid == 0 {
+        unsafe {
+            if libc::setsid() < 0 {
+                libc::_exit(1);
+            }
+            match libc::fork() {
+                -1 => libc::_exit(1),
+                0 => {}
+                _ => libc::_exit(0),
+            }
+            libc::_exit(match run_relay(relay, input) {
+                Ok(()) => 0,
+                Err(_) => 1,
+            });
+        }
+    }
+    let mut status = 0;
+    while unsafe { libc::waitpid(pid, &mut status, 0) } < 0 {
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err).chain_err(|| "Error waiting for intermediate process");
+        }
+    }
+    if status != 0 {
+        return Err("Error detaching log relay".into());
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn run_relay(relay: &LogRelay, input: c_int) -> io::Result<()> {
+    unsafe {
+        let null = libc::open(b"/dev/null\0".as_ptr() as *const libc::c_char, libc::O_RDWR);
+        if null < 0 || libc::dup2(input, 0) < 0 || libc::dup2(null, 1) < 0
+            || libc::dup2(null, 2) < 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    close_from(3, false)?;
+    reset_signals()?;
+    relay.run(0)
+}
+
+#[cfg(unix)]
+fn reset_signals() -> io::Result<()> {
+    unsafe {
+        let mut signals: libc::sigset_t = mem::zeroed();
+        libc::sigemptyset(&mut signals);
+        libc::sigprocmask(libc::SIG_SETMASK, &signals, ptr::null_mut());
+        for signal in 1..MAX_SIGNAL + 1 {
+            if signal != libc::SIGKILL && signal != libc::SIGSTOP {
+                libc::signal(signal, libc::SIG_DFL);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn detach(pid_fd: c_int) -> io::Result<()> {
+    unsafe {
+        if libc::setsid() < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        match libc::fork() {
+            -1 => return Err(io::Error::last_os_error()),
+            0 => {}
+            pid => {
+                libc::write(
+                    pid_fd,
+                    &pid as *const pid_t as *const c_void,
+                    mem::size_of::<pid_t>(),
+                );
+                libc::_exit(0);
+            }
+        }
+    }
+    close_from(3, true)
+}
+
+#[cfg(target_os = "linux")]
+const SYS_CLOSE_RANGE: libc::c_long = 436;
+#[cfg(target_os = "linux")]
+const CLOSE_RANGE_CLOEXEC: c_uint = 1 << 2;
+
+#[cfg(unix)]
+fn close_from(first_fd: c_int, cloexec: bool) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        let flags = if cloexec { CLOSE_RANGE_CLOEXEC } else { 0 };
+        let closed = unsafe {
+            libc::syscall(SYS_CLOSE_RANGE, first_fd as c_uint, c_uint::max_value(), flags)
+        };
+        if closed == 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        // EINVAL: close_range exists but predates CLOSE_RANGE_CLOEXEC
+        match err.raw_os_error() {
+            Some(libc::ENOSYS) | Some(libc::EINVAL) => {}
+            _ => return Err(err),
+        }
+    }
+    let max_fd = match unsafe { libc::sysconf(libc::_SC_OPEN_MAX) } {
+        max_fd if max_fd > 0 => max_fd as c_int,
+        _ => 1024,
+    };
+    for fd in first_fd..max_fd {
+        unsafe {
+            if cloexec {
+                libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+            } else {
+                libc::close(fd);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+static SIGNAL_FD: AtomicIsize = AtomicIsize::new(-1);
+
+#[cfg(unix)]
+extern "C" fn relay_signal(signal: c_int, info: *mut libc::siginfo_t, _: *mut c_void) {
+    // terminal generated signals already reach children in our process group
+    if unsafe { (*info).si_code } == libc::SI_KERNEL {
+        return;
+    }
+    let fd = SIGNAL_FD.load(Ordering::SeqCst);
+    if fd >= 0 {
+        let signal = signal as u8;
+        unsafe {
+            libc::write(fd as c_int, &signal as *const u8 as *const c_void, 1);
+        }
+    }
+}
+
+#[cfg(unix)]
+struct Forwarding {
+    users: usize,
+    read_fd: c_int,
+    write_fd: c_int,
+    old_actions: Vec<(c_int, libc::sigaction)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+#[cfg(unix)]
+lazy_static! {
+    static ref FORWARDING: Mutex<Forwarding> = Mutex::new(Forwarding {
+        users: 0,
+        read_fd: -1,
+        write_fd: -1,
+        old_actions: vec![],
+        thread: None,
+    });
+    static ref FORWARDED_PIDS: Mutex<Vec<u32>> = Mutex::new(vec![]);
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<T> {
+    mutex.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+#[cfg(unix)]
+impl Forwarding {
+    fn install(&mut self) -> Result<()> {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error()).chain_err(|| "Error creating signal pipe");
+        }
+        self.read_fd = fds[0];
+        self.write_fd = fds[1];
+        unsafe {
+            libc::fcntl(self.read_fd, libc::F_SETFD, libc::FD_CLOEXEC);
+            libc::fcntl(self.write_fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        }
+        SIGNAL_FD.store(self.write_fd as isize, Ordering::SeqCst);
+        for &signal in FORWARDED_SIGNALS {
+            let mut action: libc::sigaction = unsafe { mem::zeroed() };
+            action.sa_sigaction = relay_signal as usize;
+            action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+            let mut old_action: libc::sigaction = unsafe { mem::zeroed() };
+            unsafe {
+                libc::sigemptyset(&mut action.sa_mask);
+                if libc::sigaction(signal, &action, &mut old_action) != 0 {
+                    let err = io::Error::last_os_error();
+                    self.uninstall();
+                    return Err(err).chain_err(|| "Error installing signal handler");
+                }
+            }
+            self.old_actions.push((signal, old_action));
+        }
+        let read_fd = self.read_fd;
+        self.thread = Some(thread::spawn(move || loop {
+            let mut signal: u8 = 0;
+            let len = unsafe { libc::read(read_fd, &mut signal as *mut u8 as *mut c_void, 1) };
+            if len < 0 && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            if len != 1 || signal == 0 {
+                break;
+            }
+            let pids = lock(&FORWARDED_PIDS);
+            debug!("forwarding signal {} to {:?}", signal, *pids);
+            for pid in pids.iter() {
+                unsafe {
+                    libc::kill(*pid as pid_t, c_int::from(signal));
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    fn uninstall(&mut self) {
+        for (signal, old_action) in self.old_actions.drain(..) {
+            unsafe {
+                libc::sigaction(signal, &old_action, ptr::null_mut());
+            }
+        }
+        SIGNAL_FD.store(-1, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let stop: u8 = 0;
+            unsafe {
+                libc::write(self.write_fd, &stop as *const u8 as *const c_void, 1);
+            }
+            let _ = thread.join();
+        }
+        unsafe {
+            libc::close(self.read_fd);
+            libc::close(self.write_fd);
+        }
+        self.read_fd = -1;
+        self.write_fd = -1;
+    }
+}
+
+// Handlers are process-wide, so concurrent waits share one installation and it is only removed
+// when the last of them is done
+#[cfg(unix)]
+struct SignalForwarder {
+    pids: Vec<u32>,
+}
+
+#[cfg(unix)]
+impl SignalForwarder {
+    fn new() -> Result<Self> {
+        let mut forwarding = lock(&FORWARDING);
+        if forwarding.users == 0 {
+            forwarding.install()?;
+        }
+        forwarding.users += 1;
+        Ok(SignalForwarder { pids: vec![] })
+    }
+
+    fn forward(&mut self, pids: Vec<u32>) {
+        lock(&FORWARDED_PIDS).extend(&pids);
+        self.pids = pids;
+    }
+}
+
+#[cfg(unix)]
+impl Drop for SignalForwarder {
+    fn drop(&mut self) {
+        {
+            let mut forwarded = lock(&FORWARDED_PIDS);
+            for pid in &self.pids {
+                if let Some(pos) = forwarded.iter().position(|forwarded| forwarded == pid) {
+                    forwarded.swap_remove(pos);
+                }
+            }
+        }
+        let mut forwarding = lock(&FORWARDING);
+        forwarding.users -= 1;
+        if forwarding.users == 0 {
+            forwarding.uninstall();
+        }
+    }
+}
+
+#[cfg(unix)]
+const FORWARDED_SIGNALS: &[c_int] = &[libc::SIGINT, libc::SIGTERM];
+
+fn init_cmd(cmd_line: &CmdLine, work_dir: Option<&Path>, opt: &Options) -> Command {
+    let mut cmd = Command::new(&cmd_line.cmd);
+    cmd.args(&cmd_line.args);
+    cmd.before_exec(reset_signals);
     if let Some(ref path) = work_dir {
         if path.exists() {
             cmd.current_dir(path);
@@ -44,6 +542,163 @@ fn init_cmd(cmd_line: CmdLine, work_dir: Option<&Path>, opt: &Options) -> Comman
                 .stderr(Stdio::inherit())
                 .stdin(Stdio::inherit());
         }
+        Io::Log(ref path) => {
+            let log = relay_log(path, cmd_line).and_then(|stdout| {
+                let stderr = stdout.try_clone()?;
+                Ok((stdout, stderr))
+            });
+            match log {
+                Ok((stdout, stderr)) => {
+                    debug!("logging output to '{}'", path.display());
+                    cmd.stdout(stdout).stderr(stderr).stdin(Stdio::null());
+                }
+                Err(err) => {
+                    warn!("Not logging output: {}", err);
+                    cmd.stdout(Stdio::null())
+                        .stderr(Stdio::null())
+                        .stdin(Stdio::null());
+                }
+            }
+        }
     }
     cmd
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    struct DummyLaunch {
+        cmd_lines: Vec<(&'static str, Vec<&'static str>)>,
+    }
+
+    impl Launch for DummyLaunch {
+        fn command_lines(&self, _args: Vec<String>) -> Result<Vec<CmdLine>> {
+            Ok(self.cmd_lines
+                .iter()
+                .map(|&(cmd, ref args)| CmdLine {
+                    cmd: cmd.to_owned(),
+                    args: args.iter().map(|arg| (*arg).to_owned()).collect(),
+                })
+                .collect())
+        }
+    }
+
+    #[test]
+    fn detach() {
+        let launch = DummyLaunch {
+            cmd_lines: vec![("true", vec![]), ("true", vec![])],
+        };
+        match launch.launch(vec![], LaunchMode::Detach) {
+            Ok(LaunchOutcome::Spawned(pids)) => assert_that!(pids).has_length(2),
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn wait() {
+        let launch = DummyLaunch {
+            cmd_lines: vec![("true", vec![]), ("sh", vec!["-c", "exit 3"])],
+        };
+        match launch.launch(vec![], LaunchMode::Wait) {
+            Ok(LaunchOutcome::Exited(statuses)) => {
+                let codes: Vec<_> = statuses.iter().map(|status| status.code()).collect();
+                assert_that!(codes).is_equal_to(vec![Some(0), Some(3)]);
+            }
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn wait_spawn_error_kills_spawned() {
+        let tmp_dir = ::tempdir::TempDir::new("launch").unwrap();
+        let marker = tmp_dir.path().join("marker");
+        ::std::env::set_var("LUNCH_TEST_MARKER", &marker);
+        let launch = DummyLaunch {
+            cmd_lines: vec![
+                ("sh", vec!["-c", "sleep 0.3; touch \"$LUNCH_TEST_MARKER\""]),
+                ("/nonexistent/command", vec![]),
+            ],
+        };
+        assert_that!(launch.launch(vec![], LaunchMode::Wait)).is_err();
+        thread::sleep(::std::time::Duration::from_millis(600));
+        assert_that!(marker.exists()).is_false();
+    }
+
+    #[test]
+    fn concurrent_waits_share_forwarding() {
+        let slow = thread::spawn(|| {
+            let launch = DummyLaunch {
+                cmd_lines: vec![("sleep", vec!["0.5"])],
+            };
+            launch.launch(vec![], LaunchMode::Wait).is_ok()
+        });
+        thread::sleep(::std::time::Duration::from_millis(100));
+        let launch = DummyLaunch {
+            cmd_lines: vec![("true", vec![])],
+        };
+        assert_that!(launch.launch(vec![], LaunchMode::Wait)).is_ok();
+        assert_that!(SIGNAL_FD.load(Ordering::SeqCst)).is_not_equal_to(-1);
+        assert_that!(slow.join().unwrap()).is_true();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn detach_new_session() {
+        let launch = DummyLaunch {
+            cmd_lines: vec![("sleep", vec!["5"])],
+        };
+        let pid = match launch.launch(vec![], LaunchMode::Detach) {
+            Ok(LaunchOutcome::Spawned(pids)) => pids[0],
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        };
+        let stat = ::std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+        let fields: Vec<_> = stat.rsplit(')').next().unwrap().split_whitespace().collect();
+        let session: pid_t = fields[3].parse().unwrap();
+        unsafe {
+            libc::kill(pid as pid_t, libc::SIGKILL);
+        }
+        assert_that!(stat.contains("(sleep)")).is_true();
+        assert_that!(session).is_not_equal_to(unsafe { libc::getsid(0) });
+        assert_that!(session).is_not_equal_to(pid as pid_t);
+    }
+
+    #[test]
+    fn exit_code() {
+        let outcome = LaunchOutcome::Exited(vec![
+            ExitStatus::from_raw(0),
+            ExitStatus::from_raw(3 << 8),
+            ExitStatus::from_raw(1 << 8),
+        ]);
+        assert_that!(outcome.exit_code()).is_equal_to(3);
+        let outcome = LaunchOutcome::Exited(vec![ExitStatus::from_raw(libc::SIGKILL)]);
+        assert_that!(outcome.exit_code()).is_equal_to(128 + libc::SIGKILL);
+        let outcome = LaunchOutcome::Exited(vec![ExitStatus::from_raw(0)]);
+        assert_that!(outcome.exit_code()).is_equal_to(0);
+        assert_that!(LaunchOutcome::Spawned(vec![1]).exit_code()).is_equal_to(0);
+    }
+
+    #[test]
+    fn exec() {
+        let launch = DummyLaunch {
+            cmd_lines: vec![("true", vec![]), ("echo", vec!["hello"])],
+        };
+        match launch.launch(vec![], LaunchMode::Exec) {
+            Ok(LaunchOutcome::Exec(pids, cmd)) => {
+                assert_that!(pids).has_length(1);
+                assert_that!(cmd.cmd_line().cmd).is_equal_to("echo".to_owned());
+                assert_that!(cmd.cmd_line().args).is_equal_to(vec!["hello".to_owned()]);
+            }
+            outcome => panic!("unexpected outcome {:?}", outcome),
+        }
+    }
+
+    #[test]
+    fn spawn_error() {
+        let launch = DummyLaunch {
+            cmd_lines: vec![("/nonexistent/command", vec![])],
+        };
+        assert_that!(launch.launch(vec![], LaunchMode::Detach)).is_err();
+    }
+}
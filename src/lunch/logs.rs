@@ -0,0 +1,388 @@
+use std::env;
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use libc::{self, c_int, c_void};
+
+use lunch::errors::*;
+use lunch::exec::CmdLine;
+
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+const MAX_ROTATIONS: u32 = 3;
+const HEADER_PREFIX: &str = "==> ";
+const TIMESTAMP_LEN: usize = 29;
+
+pub fn log_dir() -> Result<PathBuf> {
+    let state_home = match env::var_os("XDG_STATE_HOME") {
+        Some(ref dir) if Path::new(dir).is_absolute() => PathBuf::from(dir),
+        _ => env::var_os("HOME")
+            .map(PathBuf::from)
+            .ok_or("Unable to determine home directory")?
+            .join(".local/state"),
+    };
+    Ok(state_home.join("lunch/logs"))
+}
+
+pub fn log_path(id: &str) -> Result<PathBuf> {
+    Ok(log_dir()?.join(format!("{}.log", id)))
+}
+
+// Everything after `new` must stay async-signal-safe: `run` is called in a process forked from a
+// possibly multi-threaded host, so it only makes system calls on data prepared beforehand.
+pub struct LogRelay {
+    path: CString,
+    rotated: Vec<CString>,
+    max_size: u64,
+    utc_offset: i32,
+}
+
+impl LogRelay {
+    pub fn new(path: &Path) -> Result<LogRelay> {
+        Self::with_limits(path, MAX_LOG_SIZE, MAX_ROTATIONS)
+    }
+
+    fn with_limits(path: &Path, max_size: u64, rotations: u32) -> Result<LogRelay> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).chain_err(|| "Error creating log directory")?;
+        }
+        let c_path = |path: &Path| {
+            CString::new(path.as_os_str().as_bytes())
+                .chain_err(|| format!("Invalid log file '{}'", path.display()))
+        };
+        Ok(LogRelay {
+            path: c_path(path)?,
+            rotated: (1..rotations + 1)
+                .map(|n| c_path(&rotated_path(path, n)))
+                .collect::<Result<_>>()?,
+            max_size,
+            utc_offset: Local::now().offset().local_minus_utc(),
+        })
+    }
+
+    pub fn write_header(&self, cmd_line: &CmdLine) -> Result<()> {
+        let fd = self.open().chain_err(|| "Error opening log file")?;
+        let mut header = HEADER_PREFIX.as_bytes().to_vec();
+        header.extend_from_slice(&self.timestamp());
+        let command = format!(" {} {}\n", cmd_line.cmd, cmd_line.args.join(" "));
+        header.extend_from_slice(command.as_bytes());
+        let written = write_all(fd, &header);
+        unsafe {
+            libc::close(fd);
+        }
+        written.chain_err(|| "Error writing log file")
+    }
+
+    pub fn run(&self, input: c_int) -> io::Result<()> {
+        let mut fd = self.open()?;
+        let mut buf = [0u8; 4096];
+        let mut line_start = true;
+        loop {
+            let len = unsafe { libc::read(input, buf.as_mut_ptr() as *mut c_void, buf.len()) };
+            if len < 0 {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                return Err(err);
+            }
+            if len == 0 {
+                if !line_start {
+                    write_all(fd, b"\n")?;
+                }
+                unsafe {
+                    libc::close(fd);
+                }
+                return Ok(());
+            }
+            let mut rest = &buf[..len as usize];
+            while !rest.is_empty() {
+                if line_start {
+                    if file_size(fd)? >= self.max_size {
+                        unsafe {
+                            libc::close(fd);
+                        }
+                        fd = self.open()?;
+                    }
+                    let mut stamp = [b' '; TIMESTAMP_LEN + 1];
+                    stamp[..TIMESTAMP_LEN].copy_from_slice(&self.timestamp());
+                    write_all(fd, &stamp)?;
+                }
+                let end = rest.iter()
+                    .position(|&b| b == b'\n')
+                    .map(|pos| pos + 1)
+                    .unwrap_or_else(|| rest.len());
+                write_all(fd, &rest[..end])?;
+                line_start = rest[end - 1] == b'\n';
+                rest = &rest[end..];
+            }
+        }
+    }
+
+    fn open(&self) -> io::Result<c_int> {
+        let fd = self.open_append()?;
+        if file_size(fd)? < self.max_size {
+            return Ok(fd);
+        }
+        unsafe {
+            libc::close(fd);
+        }
+        self.rotate();
+        self.open_append()
+    }
+
+    fn open_append(&self) -> io::Result<c_int> {
+        let flags = libc::O_WRONLY | libc::O_CREAT | libc::O_APPEND | libc::O_CLOEXEC;
+        match unsafe { libc::open(self.path.as_ptr(), flags, 0o644) } {
+            -1 => Err(io::Error::last_os_error()),
+            fd => Ok(fd),
+        }
+    }
+
+    fn rotate(&self) {
+        for n in (1..self.rotated.len()).rev() {
+            unsafe {
+                libc::rename(self.rotated[n - 1].as_ptr(), self.rotated[n].as_ptr());
+            }
+        }
+        if let Some(first) = self.rotated.first() {
+            unsafe {
+                libc::rename(self.path.as_ptr(), first.as_ptr());
+            }
+        }
+    }
+
+    fn timestamp(&self) -> [u8; TIMESTAMP_LEN] {
+        let mut now: libc::timespec = unsafe { ::std::mem::zeroed() };
+        unsafe {
+            libc::clock_gettime(libc::CLOCK_REALTIME, &mut now);
+        }
+        format_timestamp(now.tv_sec as i64, now.tv_nsec as u32 / 1_000_000, self.utc_offset)
+    }
+}
+
+fn file_size(fd: c_int) -> io::Result<u64> {
+    let mut stat: libc::stat = unsafe { ::std::mem::zeroed() };
+    if unsafe { libc::fstat(fd, &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(stat.st_size as u64)
+}
+
+fn write_all(fd: c_int, mut buf: &[u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        let len = unsafe { libc::write(fd, buf.as_ptr() as *const c_void, buf.len()) };
+        if len < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+        buf = &buf[len as usize..];
+    }
+    Ok(())
+}
+
+// Same layout as chrono's "%Y-%m-%d %H:%M:%S%.3f %z", without touching the time zone database
+fn format_timestamp(secs: i64, millis: u32, utc_offset: i32) -> [u8; TIMESTAMP_LEN] {
+    let local = secs + i64::from(utc_offset);
+    let days = if local >= 0 { local } else { local - 86_399 } / 86_400;
+    let day_secs = (local - days * 86_400) as u32;
+    let (year, month, day) = civil_from_days(days);
+    let mut stamp = *b"0000-00-00 00:00:00.000 +0000";
+    put_digits(&mut stamp[0..4], year as u32);
+    put_digits(&mut stamp[5..7], month);
+    put_digits(&mut stamp[8..10], day);
+    put_digits(&mut stamp[11..13], day_secs / 3600);
+    put_digits(&mut stamp[14..16], day_secs / 60 % 60);
+    put_digits(&mut stamp[17..19], day_secs % 60);
+    put_digits(&mut stamp[20..23], millis);
+    if utc_offset < 0 {
+        stamp[24] = b'-';
+    }
+    let offset_mins = utc_offset.abs() as u32 / 60;
+    put_digits(&mut stamp[25..27], offset_mins / 60);
+    put_digits(&mut stamp[27..29], offset_mins % 60);
+    stamp
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn put_digits(buf: &mut [u8], mut value: u32) {
+    for digit in buf.iter_mut().rev() {
+        *digit = b'0' + (value % 10) as u8;
+        value /= 10;
+    }
+}
+
+fn rotated_path(path: &Path, n: u32) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", n));
+    PathBuf::from(rotated)
+}
+
+pub fn last_launch<R: BufRead>(input: R) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    for line in input.lines() {
+        let line = line?;
+        if line.starts_with(HEADER_PREFIX) {
+            lines.clear();
+        }
+        lines.push(line);
+    }
+    Ok(lines)
+}
+
+pub fn read_last_launch(path: &Path) -> Result<Vec<String>> {
+    let file = File::open(path).chain_err(|| format!("No log file '{}'", path.display()))?;
+    last_launch(BufReader::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use chrono::{DateTime, FixedOffset, TimeZone};
+    use tempdir::TempDir;
+
+    fn cmd_line() -> CmdLine {
+        CmdLine {
+            cmd: "echo".to_owned(),
+            args: vec!["-n".to_owned()],
+        }
+    }
+
+    fn contents(path: &Path) -> String {
+        let mut contents = String::new();
+        File::open(path)
+            .unwrap()
+            .read_to_string(&mut contents)
+            .unwrap();
+        contents
+    }
+
+    fn relay(relay: &LogRelay, input: &[u8]) {
+        let mut fds = [0; 2];
+        assert_that!(unsafe { libc::pipe(fds.as_mut_ptr()) }).is_equal_to(0);
+        write_all(fds[1], input).unwrap();
+        unsafe {
+            libc::close(fds[1]);
+        }
+        relay.run(fds[0]).unwrap();
+        unsafe {
+            libc::close(fds[0]);
+        }
+    }
+
+    #[test]
+    fn header() {
+        let tmp_dir = TempDir::new("logs").unwrap();
+        let path = tmp_dir.path().join("logs/echo.log");
+        LogRelay::new(&path).unwrap().write_header(&cmd_line()).unwrap();
+        let contents = contents(&path);
+        assert_that!(contents.starts_with(HEADER_PREFIX)).is_true();
+        assert_that!(contents.trim_right().ends_with("echo -n")).is_true();
+    }
+
+    #[test]
+    fn last_launch_only() {
+        let input = "==> 1 echo\nold\n==> 2 echo\nnew\nnewer\n";
+        assert_that!(last_launch(input.as_bytes()).unwrap()).is_equal_to(vec![
+            "==> 2 echo".to_owned(),
+            "new".to_owned(),
+            "newer".to_owned(),
+        ]);
+    }
+
+    #[test]
+    fn timestamp_format() {
+        for &(secs, millis, offset) in &[
+            (0, 0, 0),
+            (1_792_267_932, 149, 3600),
+            (951_782_400, 999, -5 * 3600 - 30 * 60),
+            (4_107_542_399, 1, 14 * 3600),
+        ] {
+            let expected = FixedOffset::east(offset)
+                .timestamp(secs, millis * 1_000_000)
+                .format("%Y-%m-%d %H:%M:%S%.3f %z")
+                .to_string();
+            let stamp = format_timestamp(secs, millis, offset);
+            assert_that!(String::from_utf8(stamp.to_vec()).unwrap()).is_equal_to(expected);
+        }
+    }
+
+    #[test]
+    fn relay_timestamps() {
+        let tmp_dir = TempDir::new("logs").unwrap();
+        let path = tmp_dir.path().join("echo.log");
+        relay(&LogRelay::new(&path).unwrap(), b"hello\nno newline");
+        let contents = contents(&path);
+        let lines: Vec<_> = contents.lines().collect();
+        assert_that!(lines).has_length(2);
+        for (line, output) in lines.iter().zip(&["hello", "no newline"]) {
+            let (stamp, rest) = line.split_at(TIMESTAMP_LEN + 1);
+            assert_that!(rest).is_equal_to(*output);
+            assert_that!(DateTime::parse_from_str(stamp.trim_right(), "%Y-%m-%d %H:%M:%S%.3f %z"))
+                .is_ok();
+        }
+    }
+
+    #[test]
+    fn relay_rotation() {
+        let tmp_dir = TempDir::new("logs").unwrap();
+        let path = tmp_dir.path().join("echo.log");
+        relay(&LogRelay::with_limits(&path, 1, 3).unwrap(), b"one\ntwo\nthree\n");
+        assert_that!(contents(&path).trim_right().ends_with(" three")).is_true();
+        assert_that!(contents(&rotated_path(&path, 1)).trim_right().ends_with(" two")).is_true();
+        assert_that!(contents(&rotated_path(&path, 2)).trim_right().ends_with(" one")).is_true();
+        assert_that!(rotated_path(&path, 3).exists()).is_false();
+    }
+
+    #[test]
+    fn rotation() {
+        let tmp_dir = TempDir::new("logs").unwrap();
+        let path = tmp_dir.path().join("echo.log");
+        let relay = LogRelay::with_limits(&path, 1, 2).unwrap();
+        for n in 0..4 {
+            let mut file = File::create(&path).unwrap();
+            write!(file, "{}", n).unwrap();
+            drop(file);
+            unsafe {
+                libc::close(relay.open().unwrap());
+            }
+        }
+        assert_that!(contents(&path)).is_equal_to(String::new());
+        assert_that!(contents(&rotated_path(&path, 1))).is_equal_to("3".to_owned());
+        assert_that!(rotated_path(&path, 2).exists()).is_true();
+        assert_that!(rotated_path(&path, 3).exists()).is_false();
+    }
+
+    #[test]
+    fn no_rotation_below_max() {
+        let tmp_dir = TempDir::new("logs").unwrap();
+        let path = tmp_dir.path().join("echo.log");
+        File::create(&path).unwrap();
+        let relay = LogRelay::with_limits(&path, 10, 2).unwrap();
+        unsafe {
+            libc::close(relay.open().unwrap());
+        }
+        assert_that!(rotated_path(&path, 1).exists()).is_false();
+    }
+}
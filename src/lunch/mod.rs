@@ -1,24 +1,42 @@
+use std::path::PathBuf;
+
 pub mod errors;
 pub mod env;
+pub mod picker;
+pub mod pipe;
 mod freedesktop;
 mod exec;
 mod keyword;
+mod rank;
+mod normalise;
+pub mod logs;
+#[cfg(test)]
+mod test_support;
 
 pub use self::errors::*;
 
 pub use std::result::Result as StdResult;
 
 mod search;
-pub use self::search::{Search, SearchTerms};
+pub use self::search::{unambiguous, unambiguous_match, Field, Match, Search, SearchTerms};
+
+mod fuzzy;
+pub use self::fuzzy::Fuzzy;
+
+pub use self::exec::CmdLine;
 
 mod launch;
-pub use self::launch::Launch;
+pub use self::launch::{ExecCommand, Launch, LaunchMode, LaunchOutcome};
+
+mod details;
+pub use self::details::Details;
 
 pub use self::env::Lunchable;
 
 enum Io {
     Suppress,
     Inherit,
+    Log(PathBuf),
 }
 
 pub struct Options {
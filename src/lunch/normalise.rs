@@ -0,0 +1,41 @@
+use caseless::default_case_fold_str;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+pub fn normalise(s: &str) -> String {
+    strip_marks(&default_case_fold_str(s))
+}
+
+pub fn strip_marks(s: &str) -> String {
+    s.nfkd().filter(|c| !is_combining_mark(*c)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn case_fold() {
+        assert_that!(normalise("KATE")).is_equal_to("kate".to_owned());
+        assert_that!(normalise("Straße")).is_equal_to("strasse".to_owned());
+    }
+
+    #[test]
+    fn strip_diacritics() {
+        assert_that!(normalise("Écrire")).is_equal_to("ecrire".to_owned());
+        assert_that!(normalise("Dateiübertragung")).is_equal_to("dateiubertragung".to_owned());
+    }
+
+    #[test]
+    fn strip_marks_keeps_case() {
+        assert_that!(strip_marks("E\u{301}crire")).is_equal_to("Ecrire".to_owned());
+        assert_that!(strip_marks("LibreOffice")).is_equal_to("LibreOffice".to_owned());
+    }
+
+    #[test]
+    fn compatibility_decomposition() {
+        assert_that!(normalise("ﬁle")).is_equal_to("file".to_owned());
+        assert_that!(normalise("Ｋｏｎｓｏｌｅ")).is_equal_to("konsole".to_owned());
+    }
+}
@@ -0,0 +1,228 @@
+use std::io::Write;
+use std::rc::Rc;
+
+use termion::{clear, cursor, style, terminal_size};
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::IntoRawMode;
+use termion::screen::AlternateScreen;
+
+use lunch::errors::*;
+use lunch::env::{LunchEnv, Lunchable};
+use lunch::rank::Rank;
+use lunch::search::Field;
+
+pub fn interactive() -> bool {
+    ::termion::is_tty(&::std::io::stdin())
+}
+
+pub fn pick(env: &LunchEnv, query: &str) -> Result<Option<Rc<Lunchable>>> {
+    let tty = ::termion::get_tty().chain_err(|| "Error opening terminal")?;
+    let input = tty.try_clone()?;
+    let mut screen = AlternateScreen::from(tty.into_raw_mode()?);
+    let (width, height) = terminal_size().unwrap_or((80, 24));
+
+    let mut picker = Picker::new(env, query);
+    write!(screen, "{}", cursor::Hide)?;
+    picker.render(&mut screen, width, height)?;
+    let mut selection = None;
+    for key in input.keys() {
+        match picker.handle(key?) {
+            Some(Outcome::Select(lunchable)) => {
+                selection = Some(lunchable);
+                break;
+            }
+            Some(Outcome::Cancel) => break,
+            None => picker.render(&mut screen, width, height)?,
+        }
+    }
+    write!(screen, "{}", cursor::Show)?;
+    screen.flush()?;
+    Ok(selection)
+}
+
+enum Outcome {
+    Select(Rc<Lunchable>),
+    Cancel,
+}
+
+struct Picker<'a> {
+    env: &'a LunchEnv,
+    ranker: Rank<Rc<Lunchable>, Lunchable>,
+    query: String,
+    selected: usize,
+    entries: Vec<Rc<Lunchable>>,
+}
+
+impl<'a> Picker<'a> {
+    fn new(env: &'a LunchEnv, query: &str) -> Self {
+        let mut picker = Picker {
+            env,
+            ranker: Rank::new(env.lunchables.clone()).fuzzy(env.fuzzy),
+            query: query.to_owned(),
+            selected: 0,
+            entries: vec![],
+        };
+        picker.filter();
+        picker
+    }
+
+    fn filter(&mut self) {
+        self.selected = 0;
+        self.entries = if self.query.trim().is_empty() {
+            self.env.lunchables.clone()
+        } else {
+            self.ranker
+                .rank(self.query.split_whitespace())
+                .into_iter()
+                .map(|m| m.item)
+                .collect()
+        };
+    }
+
+    fn handle(&mut self, key: Key) -> Option<Outcome> {
+        match key {
+            Key::Char('\n') => {
+                if let Some(lunchable) = self.entries.get(self.selected) {
+                    return Some(Outcome::Select(lunchable.clone()));
+                }
+            }
+            Key::Esc | Key::Ctrl('c') | Key::Ctrl('d') => return Some(Outcome::Cancel),
+            Key::Up | Key::Ctrl('p') => {
+                self.selected = self.selected.saturating_sub(1);
+            }
+            Key::Down | Key::Ctrl('n') => {
+                if self.selected + 1 < self.entries.len() {
+                    self.selected += 1;
+                }
+            }
+            Key::Backspace => {
+                self.query.pop();
+                self.filter();
+            }
+            Key::Ctrl('u') => {
+                self.query.clear();
+                self.filter();
+            }
+            Key::Char(c) if !c.is_control() => {
+                self.query.push(c);
+                self.filter();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn render<W: Write>(&self, out: &mut W, width: u16, height: u16) -> Result<()> {
+        let width = width as usize;
+        let rows = height.saturating_sub(1) as usize;
+        let first = if self.selected >= rows {
+            self.selected + 1 - rows
+        } else {
+            0
+        };
+        write!(out, "{}{}> {}", clear::All, cursor::Goto(1, 1), self.query)?;
+        for (row, (n, lunchable)) in self.entries
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(rows)
+            .enumerate()
+        {
+            let mut line = lunchable.to_string();
+            if let Some(comment) = lunchable.search_terms().first(Field::Comment) {
+                line = format!("{} - {}", line, comment);
+            }
+            let line: String = line.chars().take(width.saturating_sub(2)).collect();
+            write!(out, "{}", cursor::Goto(1, row as u16 + 2))?;
+            if n == self.selected {
+                write!(out, "{}> {}{}", style::Invert, line, style::Reset)?;
+            } else {
+                write!(out, "  {}", line)?;
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+    use lunch::test_support::{self, DummyLunchable};
+
+    fn env() -> LunchEnv {
+        test_support::env(
+            vec!["Firefox", "Files", "Thunderbird"]
+                .into_iter()
+                .map(|name| DummyLunchable {
+                    comment: Some("comment"),
+                    ..DummyLunchable::new(name)
+                })
+                .collect(),
+        )
+    }
+
+    fn names(picker: &Picker) -> Vec<String> {
+        picker.entries.iter().map(|e| e.to_string()).collect()
+    }
+
+    #[test]
+    fn empty_query_lists_all() {
+        let env = env();
+        let picker = Picker::new(&env, "");
+        assert_that!(names(&picker)).has_length(3);
+    }
+
+    #[test]
+    fn incremental_filter() {
+        let env = env();
+        let mut picker = Picker::new(&env, "");
+        picker.handle(Key::Char('f'));
+        picker.handle(Key::Char('i'));
+        picker.handle(Key::Char('l'));
+        assert_that!(names(&picker)[0]).is_equal_to("Files".to_owned());
+        picker.handle(Key::Backspace);
+        picker.handle(Key::Char('r'));
+        assert_that!(names(&picker)[0]).is_equal_to("Firefox".to_owned());
+    }
+
+    #[test]
+    fn navigate() {
+        let env = env();
+        let mut picker = Picker::new(&env, "");
+        picker.handle(Key::Up);
+        assert_that!(picker.selected).is_equal_to(0);
+        picker.handle(Key::Down);
+        picker.handle(Key::Down);
+        picker.handle(Key::Down);
+        assert_that!(picker.selected).is_equal_to(2);
+        match picker.handle(Key::Char('\n')) {
+            Some(Outcome::Select(lunchable)) => {
+                assert_that!(lunchable.to_string()).is_equal_to("Thunderbird".to_owned())
+            }
+            _ => panic!("expected selection"),
+        }
+    }
+
+    #[test]
+    fn cancel() {
+        let env = env();
+        let mut picker = Picker::new(&env, "");
+        match picker.handle(Key::Esc) {
+            Some(Outcome::Cancel) => {}
+            _ => panic!("expected cancel"),
+        }
+    }
+
+    #[test]
+    fn render_comment() {
+        let env = env();
+        let picker = Picker::new(&env, "thunder");
+        let mut out = vec![];
+        picker.render(&mut out, 80, 24).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_that!(out.contains("Thunderbird - comment")).is_true();
+    }
+}
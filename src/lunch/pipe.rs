@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+use std::rc::Rc;
+
+use lunch::errors::*;
+use lunch::env::{LunchEnv, Lunchable};
+use lunch::search::{unambiguous, Field, Match};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Format {
+    Plain,
+    Rofi,
+}
+
+pub fn rofi_selection() -> bool {
+    match ::std::env::var("ROFI_RETV") {
+        Ok(retv) => retv != "0",
+        Err(_) => false,
+    }
+}
+
+pub fn write_entries<W: Write>(env: &LunchEnv, out: &mut W, format: Format) -> Result<()> {
+    let names: Vec<_> = env.lunchables.iter().map(|lunchable| lunchable.to_string()).collect();
+    let mut counts = HashMap::new();
+    for name in &names {
+        *counts.entry(name.as_str()).or_insert(0) += 1;
+    }
+    for (lunchable, name) in env.lunchables.iter().zip(&names) {
+        let mut entry = match lunchable.id() {
+            Some(id) if counts[name.as_str()] > 1 => format!("{} ({})", name, id),
+            _ => name.clone(),
+        };
+        if format == Format::Rofi {
+            let options: Vec<_> = vec![("icon", lunchable.icon()), ("info", lunchable.id())]
+                .into_iter()
+                .filter_map(|(option, value)| value.map(|value| format!("{}\x1f{}", option, value)))
+                .collect();
+            if !options.is_empty() {
+                entry.push('\0');
+                entry.push_str(&options.join("\x1f"));
+            }
+        }
+        writeln!(out, "{}", entry)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+pub fn read_selection<R: BufRead>(env: &LunchEnv, input: &mut R) -> Result<Rc<Lunchable>> {
+    let mut line = String::new();
+    input
+        .read_line(&mut line)
+        .chain_err(|| "Error reading selection")?;
+    select(env, &line)
+}
+
+pub fn select_rofi(env: &LunchEnv, line: &str) -> Result<Rc<Lunchable>> {
+    let info = ::std::env::var("ROFI_INFO").ok();
+    match info.and_then(|id| select_id(env, &id)) {
+        Some(lunchable) => Ok(lunchable),
+        None => select(env, line),
+    }
+}
+
+pub fn select(env: &LunchEnv, line: &str) -> Result<Rc<Lunchable>> {
+    let line = line.trim_right_matches(|c| c == '\n' || c == '\r');
+    if let Some(lunchable) = select_suffixed(env, line) {
+        return Ok(lunchable);
+    }
+    let matches: Vec<_> = env.lunchables
+        .iter()
+        .filter(|lunchable| lunchable.to_string() == line)
+        .map(|lunchable| Match {
+            item: lunchable.clone(),
+            score: 0,
+            fields: vec![Field::Name],
+        })
+        .collect();
+    if matches.is_empty() {
+        debug!("'{}' is not an entry name, searching instead", line);
+        env.resolve(line.split_whitespace())
+    } else {
+        unambiguous(line, matches)
+    }
+}
+
+fn select_suffixed(env: &LunchEnv, line: &str) -> Option<Rc<Lunchable>> {
+    if !line.ends_with(')') {
+        return None;
+    }
+    let open = line.rfind(" (")?;
+    let (name, id) = (&line[..open], &line[open + 2..line.len() - 1]);
+    select_id(env, id).and_then(|lunchable| {
+        if lunchable.to_string() == name {
+            Some(lunchable)
+        } else {
+            None
+        }
+    })
+}
+
+fn select_id(env: &LunchEnv, id: &str) -> Option<Rc<Lunchable>> {
+    env.lunchables
+        .iter()
+        .find(|lunchable| lunchable.id() == Some(id))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+    use lunch::test_support::{self, DummyLunchable};
+
+    fn env(names: &[(&'static str, Option<&'static str>)]) -> LunchEnv {
+        test_support::env(
+            names
+                .iter()
+                .map(|&(name, icon)| DummyLunchable {
+                    icon,
+                    ..DummyLunchable::new(name)
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn plain_entries() {
+        let env = env(&[("Firefox", Some("firefox")), ("Files", None)]);
+        let mut out = vec![];
+        write_entries(&env, &mut out, Format::Plain).unwrap();
+        assert_that!(String::from_utf8(out).unwrap()).is_equal_to("Firefox\nFiles\n".to_owned());
+    }
+
+    #[test]
+    fn rofi_entries() {
+        let env = env(&[("Firefox", Some("firefox")), ("Files", None)]);
+        let mut out = vec![];
+        write_entries(&env, &mut out, Format::Rofi).unwrap();
+        assert_that!(String::from_utf8(out).unwrap())
+            .is_equal_to("Firefox\0icon\x1ffirefox\nFiles\n".to_owned());
+    }
+
+    #[test]
+    fn select_exact() {
+        let env = env(&[("Firefox", None), ("Firefox Developer Edition", None)]);
+        let mut input = "Firefox\n".as_bytes();
+        assert_that!(read_selection(&env, &mut input).map(|l| l.to_string()))
+            .is_ok()
+            .is_equal_to("Firefox".to_owned());
+    }
+
+    #[test]
+    fn select_falls_back_to_search() {
+        let env = env(&[("Firefox", None), ("Thunderbird", None)]);
+        assert_that!(select(&env, "thunder").map(|l| l.to_string()))
+            .is_ok()
+            .is_equal_to("Thunderbird".to_owned());
+    }
+
+    fn duplicates_env() -> LunchEnv {
+        test_support::env(
+            vec![("Terminal", "konsole"), ("Terminal", "xterm"), ("Files", "files")]
+                .into_iter()
+                .map(|(name, id)| DummyLunchable {
+                    id: Some(id),
+                    ..DummyLunchable::new(name)
+                })
+                .collect(),
+        )
+    }
+
+    fn selected_id(lunchable: Result<Rc<Lunchable>>) -> Option<String> {
+        lunchable.unwrap().id().map(|id| id.to_owned())
+    }
+
+    #[test]
+    fn duplicate_entries() {
+        let env = duplicates_env();
+        let mut out = vec![];
+        write_entries(&env, &mut out, Format::Plain).unwrap();
+        assert_that!(String::from_utf8(out).unwrap())
+            .is_equal_to("Terminal (konsole)\nTerminal (xterm)\nFiles\n".to_owned());
+        let mut out = vec![];
+        write_entries(&env, &mut out, Format::Rofi).unwrap();
+        assert_that!(String::from_utf8(out).unwrap()).is_equal_to(
+            "Terminal (konsole)\0info\x1fkonsole\nTerminal (xterm)\0info\x1fxterm\n\
+             Files\0info\x1ffiles\n"
+                .to_owned(),
+        );
+    }
+
+    #[test]
+    fn select_duplicate_names() {
+        let env = duplicates_env();
+        assert_that!(selected_id(select(&env, "Terminal (xterm)\n")))
+            .is_equal_to(Some("xterm".to_owned()));
+        match select(&env, "Terminal") {
+            Err(Error(ErrorKind::AmbiguousMatch(..), _)) => {}
+            _ => panic!("expected ambiguous match"),
+        }
+    }
+
+    #[test]
+    fn select_rofi_info() {
+        let env = duplicates_env();
+        ::std::env::set_var("ROFI_INFO", "xterm");
+        let selected = select_rofi(&env, "Terminal (konsole)");
+        ::std::env::remove_var("ROFI_INFO");
+        assert_that!(selected_id(selected)).is_equal_to(Some("xterm".to_owned()));
+    }
+}
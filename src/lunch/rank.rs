@@ -0,0 +1,703 @@
+use std::borrow::Borrow;
+
+use super::fuzzy::{subsequence, Fuzzy};
+use super::normalise::{normalise, strip_marks};
+use super::search::{Field, Match, Search, SearchTerms};
+
+pub struct Rank<T, S: ?Sized> {
+    search_items: Vec<(T, Index)>,
+    fuzzy: Fuzzy,
+    phantom: ::std::marker::PhantomData<*mut S>,
+}
+
+impl<T, S> Rank<T, S>
+where
+    S: Search + ?Sized,
+    T: Borrow<S>,
+{
+    pub fn new(search_items: Vec<T>) -> Self {
+        Rank {
+            search_items: search_items
+                .into_iter()
+                .map(|search_item| {
+                    let index = Index::new(&search_item.borrow().search_terms());
+                    (search_item, index)
+                })
+                .collect(),
+            fuzzy: Fuzzy::default(),
+            phantom: ::std::marker::PhantomData,
+        }
+    }
+
+    pub fn fuzzy(mut self, fuzzy: Fuzzy) -> Self {
+        self.fuzzy = fuzzy;
+        self
+    }
+
+    pub fn search<Terms, Q>(&self, terms: Terms) -> Option<T>
+    where
+        Terms: Iterator<Item = Q>,
+        Q: AsRef<str>,
+        T: Clone,
+    {
+        self.rank(terms).into_iter().next().map(|m| m.item)
+    }
+
+    pub fn rank<Terms, Q>(&self, terms: Terms) -> Vec<Match<T>>
+    where
+        Terms: Iterator<Item = Q>,
+        Q: AsRef<str>,
+        T: Clone,
+    {
+        let query: Vec<String> = terms.flat_map(|term| tokenise(term.as_ref())).collect();
+        if query.is_empty() {
+            return vec![];
+        }
+        let mut matches: Vec<(Match<T>, usize)> = self.search_items
+            .iter()
+            .filter_map(|&(ref search_item, ref index)| {
+                score(&query, index, &self.fuzzy).map(|(score, fields)| {
+                    (
+                        Match {
+                            item: search_item.clone(),
+                            score,
+                            fields,
+                        },
+                        index.primary_len,
+                    )
+                })
+            })
+            .collect();
+        matches.sort_by(|&(ref a, a_len), &(ref b, b_len)| {
+            b.score.cmp(&a.score).then(a_len.cmp(&b_len))
+        });
+        matches.into_iter().map(|(m, _)| m).collect()
+    }
+}
+
+struct Index {
+    name: Vec<Word>,
+    others: Vec<(Field, String)>,
+    primary_len: usize,
+    related: Option<Box<Index>>,
+}
+
+impl Index {
+    fn new(search_terms: &SearchTerms) -> Self {
+        let name = search_terms
+            .terms
+            .iter()
+            .filter(|&&(field, _)| field == Field::Name)
+            .flat_map(|&(_, ref term)| words(term).into_iter())
+            .take(32)
+            .collect();
+        let others = search_terms
+            .terms
+            .iter()
+            .filter(|&&(field, _)| field != Field::Name)
+            .map(|&(field, ref term)| (field, term))
+            .chain(
+                search_terms
+                    .keywords
+                    .iter()
+                    .map(|keyword| (Field::Keyword, keyword)),
+            )
+            .flat_map(|(field, term)| {
+                words(term)
+                    .into_iter()
+                    .flat_map(|word| {
+                        let mut texts = word.parts;
+                        if texts.len() > 1 {
+                            texts.push(word.text);
+                        }
+                        texts.into_iter()
+                    })
+                    .map(move |text| (field, text))
+            })
+            .collect();
+        Index {
+            name,
+            others,
+            primary_len: primary_len(search_terms),
+            related: search_terms
+                .related
+                .as_ref()
+                .map(|related| Box::new(Index::new(&related.search_terms()))),
+        }
+    }
+}
+
+const FULL_NAME_BONUS: u32 = 50;
+
+fn score(query: &[String], index: &Index, fuzzy: &Fuzzy) -> Option<(u32, Vec<Field>)> {
+    let mut options = term_options(query, index, fuzzy, 0);
+    let name_len = index.name.len();
+    if let Some(ref related) = index.related {
+        let related_options = term_options(query, related, fuzzy, 32);
+        for (options, related_options) in options.iter_mut().zip(related_options) {
+            options.extend(
+                related_options
+                    .into_iter()
+                    .map(|(mask, score, field)| (mask, score / 2, field)),
+            );
+        }
+    }
+    let full_name = (1u64 << name_len) - 1;
+    best_assignment(&options, 0).map(|(score, used, mut fields)| {
+        fields.reverse();
+        if name_len > 0 && used & full_name == full_name {
+            (score + FULL_NAME_BONUS * Field::Name.weight(), fields)
+        } else {
+            (score, fields)
+        }
+    })
+}
+
+fn term_options(
+    query: &[String],
+    index: &Index,
+    fuzzy: &Fuzzy,
+    offset: usize,
+) -> Vec<Vec<(u64, u32, Field)>> {
+    query
+        .iter()
+        .map(|query_word| {
+            let mut options: Vec<(u64, u32, Field)> = name_options(query_word, &index.name, fuzzy)
+                .into_iter()
+                .map(|(mask, score)| (mask << offset, score * Field::Name.weight(), Field::Name))
+                .collect();
+            let best_other = index
+                .others
+                .iter()
+                .filter_map(|&(field, ref word)| {
+                    score_word(query_word, word, fuzzy).map(|score| (score * field.weight(), field))
+                })
+                .max_by_key(|&(score, _)| score);
+            if let Some((score, field)) = best_other {
+                options.push((0, score, field));
+            }
+            options
+        })
+        .collect()
+}
+
+fn best_assignment(
+    options: &[Vec<(u64, u32, Field)>],
+    used: u64,
+) -> Option<(u32, u64, Vec<Field>)> {
+    if let Some((first, rest)) = options.split_first() {
+        first
+            .iter()
+            .filter(|&&(mask, _, _)| mask & used == 0)
+            .filter_map(|&(mask, score, field)| {
+                best_assignment(rest, used | mask).map(|(rest_score, rest_used, mut fields)| {
+                    fields.push(field);
+                    (score + rest_score, rest_used, fields)
+                })
+            })
+            .max_by_key(|&(score, _, _)| score)
+    } else {
+        Some((0, used, vec![]))
+    }
+}
+
+fn name_options(query_word: &str, name: &[Word], fuzzy: &Fuzzy) -> Vec<(u64, u32)> {
+    let mut options = vec![];
+    for (i, word) in name.iter().enumerate() {
+        let part_score = word.parts
+            .iter()
+            .skip(1)
+            .filter_map(|part| score_word(query_word, part, fuzzy))
+            .map(|score| score.saturating_sub(10))
+            .max();
+        let initials = word.part_initials();
+        let initials_score = if word.parts.len() > 1 && query_word.len() > 1
+            && initials.starts_with(query_word)
+        {
+            Some(80)
+        } else {
+            None
+        };
+        let best = score_word(query_word, &word.text, fuzzy)
+            .into_iter()
+            .chain(part_score)
+            .chain(initials_score)
+            .max();
+        if let Some(score) = best {
+            options.push((1 << i, score));
+        }
+    }
+    for start in 0..name.len() {
+        let mut word_initials = String::new();
+        let mut part_initials = String::new();
+        let mut mask = 0;
+        for (i, word) in name.iter().enumerate().skip(start) {
+            word_initials.push_str(&word.text[..word.text.chars().next().unwrap().len_utf8()]);
+            part_initials.push_str(&word.part_initials());
+            mask |= 1 << i;
+            if i > start && (word_initials == query_word || part_initials == query_word) {
+                let consumed = (i - start + 1) as u32;
+                options.push((mask, 70 + 30 * consumed / name.len() as u32));
+            }
+        }
+    }
+    options
+}
+
+struct Word {
+    text: String,
+    parts: Vec<String>,
+}
+
+impl Word {
+    fn part_initials(&self) -> String {
+        self.parts
+            .iter()
+            .filter_map(|part| part.chars().next())
+            .collect()
+    }
+}
+
+fn words(s: &str) -> Vec<Word> {
+    strip_marks(s)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| Word {
+            text: normalise(word),
+            parts: camel_case_parts(word)
+                .into_iter()
+                .map(normalise)
+                .filter(|part| !part.is_empty())
+                .collect(),
+        })
+        .filter(|word| !word.text.is_empty())
+        .collect()
+}
+
+fn camel_case_parts(word: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = word.char_indices().collect();
+    let mut parts = vec![];
+    let mut start = 0;
+    for n in 1..chars.len() {
+        let (i, c) = chars[n];
+        let prev = chars[n - 1].1;
+        let next_lower = chars.get(n + 1).map(|&(_, c)| c.is_lowercase()) == Some(true);
+        if c.is_uppercase() && (prev.is_lowercase() || (prev.is_uppercase() && next_lower)) {
+            parts.push(&word[start..i]);
+            start = i;
+        }
+    }
+    parts.push(&word[start..]);
+    parts
+}
+
+#[cfg(test)]
+mod camel_case_parts_tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn single() {
+        assert_that!(camel_case_parts("Writer")).is_equal_to(vec!["Writer"]);
+        assert_that!(camel_case_parts("gimp")).is_equal_to(vec!["gimp"]);
+        assert_that!(camel_case_parts("GIMP")).is_equal_to(vec!["GIMP"]);
+    }
+
+    #[test]
+    fn camel_case() {
+        assert_that!(camel_case_parts("LibreOffice")).is_equal_to(vec!["Libre", "Office"]);
+        assert_that!(camel_case_parts("RasterGraphics")).is_equal_to(vec!["Raster", "Graphics"]);
+    }
+
+    #[test]
+    fn leading_acronym() {
+        assert_that!(camel_case_parts("KDEConnect")).is_equal_to(vec!["KDE", "Connect"]);
+    }
+}
+
+fn primary_len(search_terms: &SearchTerms) -> usize {
+    search_terms
+        .first(Field::Name)
+        .map(|name| name.chars().count())
+        .unwrap_or(0)
+}
+
+fn score_word(query_word: &str, word: &str, fuzzy: &Fuzzy) -> Option<u32> {
+    let (query_len, word_len) = (query_word.chars().count(), word.chars().count());
+    if query_word == word {
+        Some(100)
+    } else if word.starts_with(query_word) {
+        Some(50 + (50 * query_len / word_len) as u32)
+    } else if word.contains(query_word) {
+        Some(25 + (25 * query_len / word_len) as u32)
+    } else if let Some(similarity) = fuzzy.similarity(query_word, word) {
+        Some((45.0 * similarity) as u32)
+    } else if let Some(similarity) = fuzzy.prefix_similarity(query_word, word) {
+        Some((30.0 * similarity) as u32)
+    } else {
+        subsequence(query_word, word).map(|compactness| (20.0 * compactness) as u32)
+    }
+}
+
+fn tokenise(s: &str) -> Vec<String> {
+    normalise(s)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectral::prelude::*;
+    use std::borrow::Cow;
+    use std::rc::Rc;
+    use lunch::Lunchable;
+    use lunch::test_support::{DummyLunchable, DummySearch};
+
+    fn rank<'a>(items: Vec<DummySearch<'a>>, query: &str) -> Vec<Match<Rc<DummySearch<'a>>>> {
+        Rank::<Rc<DummySearch>, DummySearch>::new(items.into_iter().map(Rc::new).collect())
+            .rank(query.split(' '))
+    }
+
+    #[test]
+    fn no_match() {
+        let matches = rank(
+            vec![DummySearch::new(vec![(Field::Name, "Firefox")], vec![])],
+            "chromium",
+        );
+        assert_that!(matches).has_length(0);
+    }
+
+    #[test]
+    fn empty_query() {
+        let matches = rank(
+            vec![DummySearch::new(vec![(Field::Name, "Firefox")], vec![])],
+            "",
+        );
+        assert_that!(matches).has_length(0);
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let matches = rank(
+            vec![DummySearch::new(vec![(Field::Name, "Firefox")], vec![])],
+            "FIREFOX",
+        );
+        assert_that!(matches).has_length(1);
+    }
+
+    #[test]
+    fn all_query_words_must_match() {
+        let matches = rank(
+            vec![
+                DummySearch::new(
+                    vec![(Field::Name, "Firefox"), (Field::Comment, "Browse the web")],
+                    vec![],
+                ),
+            ],
+            "firefox mail",
+        );
+        assert_that!(matches).has_length(0);
+    }
+
+    #[test]
+    fn keyword_match() {
+        let matches = rank(
+            vec![
+                DummySearch::new(vec![(Field::Name, "Firefox")], vec!["internet"]),
+            ],
+            "internet",
+        );
+        assert_that!(matches).has_length(1);
+    }
+
+    #[test]
+    fn matched_fields() {
+        let matches = rank(
+            vec![
+                DummySearch::new(
+                    vec![(Field::Name, "Firefox"), (Field::Comment, "Browse the web")],
+                    vec!["internet"],
+                ),
+            ],
+            "internet firefox web",
+        );
+        assert_that!(matches[0].fields).is_equal_to(vec![
+            Field::Keyword,
+            Field::Name,
+            Field::Comment,
+        ]);
+    }
+
+    #[test]
+    fn exact_before_prefix_before_substring() {
+        let matches = rank(
+            vec![
+                DummySearch::new(vec![(Field::Name, "Gedit Plugins")], vec![]),
+                DummySearch::new(vec![(Field::Name, "Edit")], vec![]),
+                DummySearch::new(vec![(Field::Name, "Editor")], vec![]),
+            ],
+            "edit",
+        );
+        let names: Vec<_> = matches
+            .iter()
+            .map(|m| m.item.search_terms.terms[0].1.to_string())
+            .collect();
+        assert_that!(names).is_equal_to(vec![
+            "Edit".to_owned(),
+            "Editor".to_owned(),
+            "Gedit Plugins".to_owned(),
+        ]);
+    }
+
+    #[test]
+    fn search_best() {
+        let best = Rank::<Rc<DummySearch>, DummySearch>::new(vec![
+            Rc::new(DummySearch::new(vec![(Field::Name, "Terminal Emulator")], vec![])),
+            Rc::new(DummySearch::new(vec![(Field::Name, "Terminal")], vec![])),
+        ]).search(vec!["terminal"].into_iter());
+        assert_that!(best)
+            .is_some()
+            .map(|best| &best.search_terms.terms[0].1)
+            .is_equal_to(Cow::Borrowed("Terminal"));
+    }
+
+    #[test]
+    fn name_before_keyword_before_comment() {
+        let matches = rank(
+            vec![
+                DummySearch::new(vec![(Field::Comment, "Edit text files")], vec![]),
+                DummySearch::new(vec![(Field::Name, "Writer")], vec!["text"]),
+                DummySearch::new(vec![(Field::Name, "Text")], vec![]),
+            ],
+            "text",
+        );
+        let scores: Vec<_> = matches.iter().map(|m| m.score).collect();
+        assert_that!(scores).is_equal_to(vec![
+            (100 + FULL_NAME_BONUS) * Field::Name.weight(),
+            100 * Field::Keyword.weight(),
+            100 * Field::Comment.weight(),
+        ]);
+    }
+
+    #[test]
+    fn generic_name_match() {
+        let matches = rank(
+            vec![
+                DummySearch::new(vec![(Field::Name, "Image Viewer")], vec![]),
+                DummySearch::new(
+                    vec![
+                        (Field::Name, "GIMP"),
+                        (Field::GenericName, "Image Editor"),
+                        (Field::Category, "Graphics"),
+                    ],
+                    vec![],
+                ),
+            ],
+            "image editor",
+        );
+        assert_that!(matches).has_length(1);
+        assert_that!(matches[0].item.search_terms.terms[0].1).is_equal_to(Cow::Borrowed("GIMP"));
+    }
+
+    #[test]
+    fn typo() {
+        let matches = rank(
+            vec![
+                DummySearch::new(vec![(Field::Name, "Firefox")], vec![]),
+                DummySearch::new(vec![(Field::Name, "Thunderbird")], vec![]),
+            ],
+            "fierfox",
+        );
+        assert_that!(matches).has_length(1);
+        assert_that!(matches[0].item.search_terms.terms[0].1).is_equal_to(Cow::Borrowed("Firefox"));
+    }
+
+    #[test]
+    fn typo_ranked_below_exact() {
+        let matches = rank(
+            vec![
+                DummySearch::new(vec![(Field::Name, "Fiddler")], vec![]),
+                DummySearch::new(vec![(Field::Name, "Fidler")], vec![]),
+            ],
+            "fidler",
+        );
+        assert_that!(matches).has_length(2);
+        assert_that!(matches[0].item.search_terms.terms[0].1).is_equal_to(Cow::Borrowed("Fidler"));
+    }
+
+    #[test]
+    fn subsequence() {
+        let matches = rank(
+            vec![DummySearch::new(vec![(Field::Name, "Thunderbird")], vec![])],
+            "tbird",
+        );
+        assert_that!(matches).has_length(1);
+    }
+
+    #[test]
+    fn threshold() {
+        let matches = Rank::<Rc<DummySearch>, DummySearch>::new(vec![
+            Rc::new(DummySearch::new(vec![(Field::Name, "Firefox")], vec![])),
+        ]).fuzzy(Fuzzy::new(1.0))
+            .rank(vec!["fierfox"].into_iter());
+        assert_that!(matches).has_length(0);
+    }
+
+    fn names<'a>(matches: &[Match<Rc<DummySearch<'a>>>]) -> Vec<String> {
+        matches
+            .iter()
+            .map(|m| m.item.search_terms.terms[0].1.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn word_initials() {
+        let matches = rank(
+            vec![
+                DummySearch::new(vec![(Field::Name, "Lightworks")], vec![]),
+                DummySearch::new(vec![(Field::Name, "LibreOffice Writer")], vec![]),
+                DummySearch::new(vec![(Field::Name, "Visual Studio Code")], vec![]),
+            ],
+            "lw",
+        );
+        assert_that!(names(&matches)[0]).is_equal_to("LibreOffice Writer".to_owned());
+    }
+
+    #[test]
+    fn acronym() {
+        let matches = rank(
+            vec![
+                DummySearch::new(vec![(Field::Name, "GNU Image Manipulation Program")], vec![]),
+                DummySearch::new(vec![(Field::Name, "Visual Studio Code")], vec![]),
+            ],
+            "vsc",
+        );
+        assert_that!(names(&matches)).is_equal_to(vec!["Visual Studio Code".to_owned()]);
+
+        let matches = rank(
+            vec![
+                DummySearch::new(vec![(Field::Name, "Visual Studio Code")], vec![]),
+                DummySearch::new(vec![(Field::Name, "GNU Image Manipulation Program")], vec![]),
+            ],
+            "gimp",
+        );
+        assert_that!(names(&matches)).is_equal_to(vec![
+            "GNU Image Manipulation Program".to_owned(),
+        ]);
+    }
+
+    #[test]
+    fn camel_case_initials() {
+        let matches = rank(
+            vec![
+                DummySearch::new(vec![(Field::Name, "Lollypop")], vec![]),
+                DummySearch::new(vec![(Field::Name, "LibreOffice Writer")], vec![]),
+                DummySearch::new(vec![(Field::Name, "LibreOffice Calc")], vec![]),
+            ],
+            "lo w",
+        );
+        assert_that!(names(&matches)).is_equal_to(vec!["LibreOffice Writer".to_owned()]);
+    }
+
+    #[test]
+    fn query_words_match_separate_name_words() {
+        let matches = rank(
+            vec![DummySearch::new(vec![(Field::Name, "Writer")], vec![])],
+            "wri wri",
+        );
+        assert_that!(matches).has_length(0);
+
+        let matches = rank(
+            vec![DummySearch::new(vec![(Field::Name, "Writer Writer")], vec![])],
+            "wri wri",
+        );
+        assert_that!(matches).has_length(1);
+    }
+
+    #[test]
+    fn diacritics_and_case() {
+        let matches = rank(
+            vec![
+                DummySearch::new(vec![(Field::Name, "Écrire")], vec![]),
+                DummySearch::new(vec![(Field::Name, "Kate")], vec![]),
+            ],
+            "ecrire",
+        );
+        assert_that!(names(&matches)).is_equal_to(vec!["Écrire".to_owned()]);
+
+        let matches = rank(
+            vec![
+                DummySearch::new(vec![(Field::Name, "Écrire")], vec![]),
+                DummySearch::new(vec![(Field::Name, "Kate")], vec![]),
+            ],
+            "KATE",
+        );
+        assert_that!(names(&matches)).is_equal_to(vec!["Kate".to_owned()]);
+    }
+
+    #[test]
+    fn decomposed_input() {
+        let matches = rank(
+            vec![DummySearch::new(vec![(Field::Name, "Dateiu\u{308}bertragung")], vec![])],
+            "dateiübertragung",
+        );
+        assert_that!(matches).has_length(1);
+        assert_that!(matches[0].fields).is_equal_to(vec![Field::Name]);
+
+        let matches = rank(
+            vec![DummySearch::new(vec![(Field::Name, "Dateiübertragung")], vec![])],
+            "Dateiu\u{308}bertragung",
+        );
+        assert_that!(matches).has_length(1);
+    }
+
+    #[test]
+    fn related() {
+        let firefox: Rc<Lunchable> = Rc::new(DummyLunchable::new("Firefox"));
+        let private_window: Rc<Lunchable> = Rc::new(DummyLunchable {
+            related: Some(firefox.clone()),
+            ..DummyLunchable::new("New Private Window")
+        });
+        let matches = Rank::<Rc<Lunchable>, Lunchable>::new(vec![
+            firefox.clone(),
+            private_window.clone(),
+        ]).rank(vec!["firefox", "private"].into_iter());
+        let names: Vec<_> = matches.iter().map(|m| m.item.to_string()).collect();
+        assert_that!(names).is_equal_to(vec!["New Private Window".to_owned()]);
+
+        let matches = Rank::<Rc<Lunchable>, Lunchable>::new(vec![private_window, firefox])
+            .rank(vec!["firefox"].into_iter());
+        let names: Vec<_> = matches.iter().map(|m| m.item.to_string()).collect();
+        assert_that!(names).is_equal_to(vec![
+            "Firefox".to_owned(),
+            "New Private Window".to_owned(),
+        ]);
+    }
+
+    #[test]
+    fn rank_repeatedly() {
+        let ranker = Rank::<Rc<DummySearch>, DummySearch>::new(vec![
+            Rc::new(DummySearch::new(vec![(Field::Name, "Firefox")], vec![])),
+            Rc::new(DummySearch::new(vec![(Field::Name, "Konsole")], vec![])),
+        ]);
+        assert_that!(names(&ranker.rank(vec!["fire"].into_iter())))
+            .is_equal_to(vec!["Firefox".to_owned()]);
+        assert_that!(names(&ranker.rank(vec!["kon"].into_iter())))
+            .is_equal_to(vec!["Konsole".to_owned()]);
+    }
+
+    #[test]
+    fn full_name_bonus() {
+        let matches = rank(
+            vec![
+                DummySearch::new(vec![(Field::Name, "Terminal Emulator")], vec![]),
+                DummySearch::new(vec![(Field::Name, "Terminal")], vec![]),
+            ],
+            "terminal",
+        );
+        assert_that!(matches[0].score).is_greater_than(matches[1].score);
+    }
+}
@@ -1,19 +1,29 @@
 use std::borrow::Cow;
-use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::fmt::{Debug, Display, Formatter, Result as FmtResult};
 use std::rc::Rc;
 
 use lunch::Lunchable;
+use lunch::errors::*;
 
 pub trait Search {
     fn search_terms<'a>(&'a self) -> SearchTerms<'a>;
 }
 
 pub struct SearchTerms<'a> {
-    pub terms: Vec<Cow<'a, str>>,
+    pub terms: Vec<(Field, Cow<'a, str>)>,
     pub keywords: Vec<Cow<'a, str>>,
     pub related: Option<Rc<Lunchable>>,
 }
 
+impl<'a> SearchTerms<'a> {
+    pub fn first(&self, field: Field) -> Option<&str> {
+        self.terms
+            .iter()
+            .find(|&&(f, _)| f == field)
+            .map(|&(_, ref term)| term.as_ref())
+    }
+}
+
 impl<'a> Debug for SearchTerms<'a> {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         f.debug_struct("SearchTerms")
@@ -22,3 +32,121 @@ impl<'a> Debug for SearchTerms<'a> {
             .finish()
     }
 }
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Field {
+    Name,
+    GenericName,
+    Keyword,
+    Translation,
+    Comment,
+    Category,
+}
+
+impl Field {
+    pub fn weight(&self) -> u32 {
+        use self::Field::*;
+        match *self {
+            Name => 10,
+            GenericName => 7,
+            Keyword => 5,
+            Translation => 3,
+            Comment => 2,
+            Category => 1,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Match<T> {
+    pub item: T,
+    pub score: u32,
+    pub fields: Vec<Field>,
+}
+
+pub fn unambiguous<T: Display>(term: &str, matches: Vec<Match<T>>) -> Result<T> {
+    unambiguous_match(term, matches).map(|m| m.item)
+}
+
+pub fn unambiguous_match<T: Display>(term: &str, matches: Vec<Match<T>>) -> Result<Match<T>> {
+    let top_score = match matches.first() {
+        Some(m) => m.score,
+        None => return Err(ErrorKind::NoMatchFound(term.to_owned()).into()),
+    };
+    let mut best: Vec<Match<T>> = matches
+        .into_iter()
+        .take_while(|m| m.score == top_score)
+        .collect();
+    if best.len() == 1 {
+        Ok(best.remove(0))
+    } else {
+        let candidates = best.iter().map(|m| m.item.to_string()).collect();
+        Err(ErrorKind::AmbiguousMatch(term.to_owned(), candidates).into())
+    }
+}
+
+#[cfg(test)]
+mod unambiguous_tests {
+    use super::*;
+    use spectral::prelude::*;
+
+    #[test]
+    fn no_match() {
+        let res = unambiguous::<String>("term", vec![]);
+        assert_that!(res).is_err();
+        match res.unwrap_err().kind() {
+            &ErrorKind::NoMatchFound(ref term) => assert_that!(term.as_str()).is_equal_to("term"),
+            kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn single_best() {
+        let res = unambiguous(
+            "term",
+            vec![
+                Match {
+                    item: "a".to_owned(),
+                    score: 2,
+                    fields: vec![],
+                },
+                Match {
+                    item: "b".to_owned(),
+                    score: 1,
+                    fields: vec![],
+                },
+            ],
+        );
+        assert_that!(res).is_ok().is_equal_to("a".to_owned());
+    }
+
+    #[test]
+    fn ambiguous() {
+        let res = unambiguous(
+            "term",
+            vec![
+                Match {
+                    item: "a".to_owned(),
+                    score: 2,
+                    fields: vec![],
+                },
+                Match {
+                    item: "b".to_owned(),
+                    score: 2,
+                    fields: vec![],
+                },
+                Match {
+                    item: "c".to_owned(),
+                    score: 1,
+                    fields: vec![],
+                },
+            ],
+        );
+        match res.unwrap_err().kind() {
+            &ErrorKind::AmbiguousMatch(_, ref candidates) => {
+                assert_that!(candidates).is_equal_to(&vec!["a".to_owned(), "b".to_owned()])
+            }
+            kind => panic!("unexpected error {:?}", kind),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::borrow::Cow;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::rc::Rc;
+
+use lunch::errors::*;
+use lunch::env::{LoadReport, LunchEnv};
+use lunch::{CmdLine, Details, Field, Fuzzy, Launch, Lunchable, Search, SearchTerms};
+
+#[derive(Default)]
+pub struct DummyLunchable {
+    pub name: &'static str,
+    pub id: Option<&'static str>,
+    pub comment: Option<&'static str>,
+    pub icon: Option<&'static str>,
+    pub related: Option<Rc<Lunchable>>,
+}
+
+impl DummyLunchable {
+    pub fn new(name: &'static str) -> Self {
+        DummyLunchable {
+            name,
+            ..DummyLunchable::default()
+        }
+    }
+}
+
+impl Search for DummyLunchable {
+    fn search_terms(&self) -> SearchTerms {
+        let mut terms = vec![(Field::Name, Cow::Borrowed(self.name))];
+        if let Some(comment) = self.comment {
+            terms.push((Field::Comment, Cow::Borrowed(comment)));
+        }
+        SearchTerms {
+            terms,
+            keywords: vec![],
+            related: self.related.clone(),
+        }
+    }
+}
+
+impl Launch for DummyLunchable {
+    fn command_lines(&self, _args: Vec<String>) -> Result<Vec<CmdLine>> {
+        Ok(vec![])
+    }
+}
+
+impl Details for DummyLunchable {
+    fn id(&self) -> Option<&str> {
+        self.id
+    }
+
+    fn icon(&self) -> Option<&str> {
+        self.icon
+    }
+}
+
+impl Display for DummyLunchable {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[derive(Debug)]
+pub struct DummySearch<'a> {
+    pub search_terms: SearchTerms<'a>,
+}
+
+impl<'a> DummySearch<'a> {
+    pub fn new<'b: 'a>(terms: Vec<(Field, &'b str)>, keywords: Vec<&'b str>) -> Self {
+        DummySearch {
+            search_terms: SearchTerms {
+                terms: terms
+                    .iter()
+                    .map(|&(field, s)| (field, Cow::Borrowed(s)))
+                    .collect(),
+                keywords: keywords.iter().map(|s| Cow::Borrowed(*s)).collect(),
+                related: None,
+            },
+        }
+    }
+}
+
+impl<'a> Search for DummySearch<'a> {
+    fn search_terms<'b>(&'b self) -> SearchTerms<'b> {
+        SearchTerms {
+            terms: self.search_terms.terms.clone(),
+            keywords: self.search_terms.keywords.clone(),
+            related: None,
+        }
+    }
+}
+
+pub fn env(lunchables: Vec<DummyLunchable>) -> LunchEnv {
+    LunchEnv {
+        lunchables: lunchables
+            .into_iter()
+            .map(|lunchable| Rc::new(lunchable) as Rc<Lunchable>)
+            .collect(),
+        fuzzy: Fuzzy::default(),
+        report: LoadReport::default(),
+    }
+}
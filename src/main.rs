@@ -6,13 +6,19 @@ extern crate error_chain;
 #[macro_use]
 extern crate log;
 
+use std::rc::Rc;
+
 use clap::{App, Arg};
 
 use log::LogLevelFilter;
 use env_logger::LogBuilder;
 
 use lunch::errors::*;
-use lunch::env::{LunchEnv, Lunchable};
+use lunch::env::{same_lunchable, InitOptions, LunchEnv, Lunchable};
+use lunch::{LaunchMode, LaunchOutcome, Match};
+use lunch::logs;
+use lunch::picker;
+use lunch::pipe::{self, Format};
 
 const AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
 const APP_NAME: &str = env!("CARGO_PKG_NAME");
@@ -60,8 +66,92 @@ fn run() -> Result<()> {
                 .value_name("TERMS")
                 .help("General search terms")
                 .conflicts_with("keyword")
+                .multiple(true),
+        )
+        .arg(
+            Arg::with_name("args")
+                .value_name("ARGS")
+                .help("Files or URLs to open with the launched application")
                 .multiple(true)
-                .required(true),
+                .last(true),
+        )
+        .arg(
+            Arg::with_name("matches")
+                .short("m")
+                .long("matches")
+                .help("List every match with its score instead of launching"),
+        )
+        .arg(
+            Arg::with_name("list-actions")
+                .short("l")
+                .long("list-actions")
+                .conflicts_with("action")
+                .help("List the actions of the matched application"),
+        )
+        .arg(
+            Arg::with_name("action")
+                .short("a")
+                .long("action")
+                .value_name("ACTION")
+                .takes_value(true)
+                .help("Launch the named action of the matched application"),
+        )
+        .arg(
+            Arg::with_name("show-log")
+                .long("show-log")
+                .conflicts_with_all(&["list-actions", "action", "wait"])
+                .help("Print the output of the most recent launch of the matched application"),
+        )
+        .arg(
+            Arg::with_name("print")
+                .short("p")
+                .long("print")
+                .conflicts_with_all(&["keyword", "terms", "select", "rofi"])
+                .help("Print every entry name, one per line, for dmenu or fzf"),
+        )
+        .arg(
+            Arg::with_name("select")
+                .short("s")
+                .long("select")
+                .conflicts_with_all(&["keyword", "terms", "rofi"])
+                .help("Launch the entry named on standard input"),
+        )
+        .arg(
+            Arg::with_name("rofi")
+                .long("rofi")
+                .conflicts_with("keyword")
+                .help("Run as a rofi script mode"),
+        )
+        .arg(
+            Arg::with_name("wait")
+                .short("w")
+                .long("wait")
+                .help("Wait for the application to exit and exit with its status"),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .short("n")
+                .long("dry-run")
+                .conflicts_with_all(&["print", "list-actions", "show-log"])
+                .help("Show what would be launched and why, without running anything"),
+        )
+        .arg(
+            Arg::with_name("load-report")
+                .long("load-report")
+                .conflicts_with_all(&["keyword", "terms", "print", "select", "rofi"])
+                .help(
+                    "List the desktop files that could not be loaded, or only leniently, and why",
+                ),
+        )
+        .arg(
+            Arg::with_name("all-translations")
+                .long("all-translations")
+                .help("Search names and keywords in every language, not just the current locale"),
+        )
+        .arg(
+            Arg::with_name("no-cache")
+                .long("no-cache")
+                .help("Read every desktop file instead of using the cache"),
         )
         .arg(
             Arg::with_name("debug")
@@ -77,6 +167,15 @@ fn run() -> Result<()> {
         )
         .get_matches();
 
+    if arg_matches.is_present("matches") && !arg_matches.is_present("keyword")
+        && !arg_matches.is_present("terms")
+    {
+        clap::Error::with_description(
+            "--matches needs a keyword or search terms",
+            clap::ErrorKind::MissingRequiredArgument,
+        ).exit();
+    }
+
     if arg_matches.is_present("trace") {
         log_builder.filter(None, LogLevelFilter::Trace);
     } else if arg_matches.is_present("debug") {
@@ -89,20 +188,184 @@ fn run() -> Result<()> {
         .init()
         .chain_err(|| "Error initialising logging")?;
 
-    let env = LunchEnv::init()?;
+    let env = LunchEnv::init_with(&InitOptions {
+        all_translations: arg_matches.is_present("all-translations"),
+        no_cache: arg_matches.is_present("no-cache"),
+    })?;
+
+    if arg_matches.is_present("load-report") {
+        for skipped in &env.report.skipped {
+            println!("{}", skipped);
+        }
+        for lenient in &env.report.loaded_leniently {
+            println!("{} (loaded leniently)", lenient);
+        }
+        return Ok(());
+    }
 
-    if let Some(keyword) = arg_matches.value_of("keyword") {
-        if let Some(lunchable) = env.keyword(keyword) {
-            return Err(lunchable.launch(vec![]));
+    if arg_matches.is_present("matches") {
+        let matches = if let Some(keyword) = arg_matches.value_of("keyword") {
+            env.keyword_matches(keyword)
+        } else if let Some(terms) = arg_matches.values_of_lossy("terms") {
+            env.rank(terms.iter())
         } else {
-            return Err(ErrorKind::NoMatchFound(keyword.to_owned()).into());
+            unreachable!()
+        };
+        for m in matches {
+            println!("{}\t{}", m.score, m.item);
         }
+        return Ok(());
+    }
+
+    if arg_matches.is_present("print") {
+        return pipe::write_entries(&env, &mut ::std::io::stdout(), Format::Plain);
+    }
+
+    if arg_matches.is_present("rofi") && !pipe::rofi_selection() {
+        return pipe::write_entries(&env, &mut ::std::io::stdout(), Format::Rofi);
+    }
+
+    let mut matched = None;
+    let resolved = if arg_matches.is_present("select") {
+        let stdin = ::std::io::stdin();
+        let lunchable = pipe::read_selection(&env, &mut stdin.lock())?;
+        Some((Ok(lunchable), String::new()))
+    } else if arg_matches.is_present("rofi") {
+        let selection = arg_matches
+            .values_of_lossy("terms")
+            .unwrap_or_default()
+            .join(" ");
+        Some((pipe::select_rofi(&env, &selection), selection))
+    } else if let Some(keyword) = arg_matches.value_of("keyword") {
+        let resolved = env.resolve_keyword_match(keyword)
+            .map(|m| keep_match(&mut matched, m));
+        Some((resolved, keyword.to_owned()))
     } else if let Some(terms) = arg_matches.values_of_lossy("terms") {
-        if let Some(lunchable) = env.search(terms.iter()) {
-            return Err(lunchable.launch(vec![]));
+        let resolved = env.resolve_match(terms.iter())
+            .map(|m| keep_match(&mut matched, m));
+        Some((resolved, terms.join(" ")))
+    } else {
+        None
+    };
+    let lunchable = match resolved {
+        Some((Ok(lunchable), _)) => Some(lunchable),
+        Some((Err(err), query)) => match *err.kind() {
+            ErrorKind::AmbiguousMatch(..) if picker::interactive() => picker::pick(&env, &query)?,
+            _ => return Err(err),
+        },
+        None if picker::interactive() => picker::pick(&env, "")?,
+        None => clap::Error::with_description(
+            "search terms or a keyword are required when not run from a terminal",
+            clap::ErrorKind::MissingRequiredArgument,
+        ).exit(),
+    };
+    let lunchable = match lunchable {
+        Some(lunchable) => lunchable,
+        None => return Ok(()),
+    };
+
+    if arg_matches.is_present("list-actions") {
+        for action in env.actions(&lunchable) {
+            println!("{}", action);
         }
-        return Err(ErrorKind::NoMatchFound(terms.join(" ")).into());
+        return Ok(());
+    }
+
+    if arg_matches.is_present("show-log") {
+        let id = lunchable
+            .id()
+            .ok_or_else(|| format!("'{}' has no log file", lunchable))?;
+        for line in logs::read_last_launch(&logs::log_path(id)?)? {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+
+    let args = arg_matches.values_of_lossy("args").unwrap_or_default();
+    let target = match arg_matches.value_of("action") {
+        Some(action) => env.action(&lunchable, action)
+            .ok_or_else(|| ErrorKind::NoMatchFound(action.to_owned()))?,
+        None => lunchable.clone(),
+    };
+    let mode = if arg_matches.is_present("wait") {
+        LaunchMode::Wait
+    } else if target.terminal() {
+        LaunchMode::Exec
     } else {
-        unreachable!()
+        LaunchMode::Detach
+    };
+
+    if arg_matches.is_present("dry-run") {
+        explain(&lunchable, matched.as_ref());
+        return dry_run(&*target, args, mode);
+    }
+
+    launch(&*target, args, mode)
+}
+
+fn keep_match(
+    matched: &mut Option<Match<Rc<Lunchable>>>,
+    m: Match<Rc<Lunchable>>,
+) -> Rc<Lunchable> {
+    let lunchable = m.item.clone();
+    *matched = Some(m);
+    lunchable
+}
+
+fn explain(lunchable: &Rc<Lunchable>, matched: Option<&Match<Rc<Lunchable>>>) {
+    println!("Entry:       {}", lunchable);
+    if let Some(location) = lunchable.location() {
+        println!("File:        {}", location.display());
+    }
+    match matched {
+        Some(m) if same_lunchable(&m.item, lunchable) => {
+            let fields: Vec<_> = m.fields.iter().map(|field| format!("{:?}", field)).collect();
+            println!("Score:       {} (matched {})", m.score, fields.join(", "));
+        }
+        _ => println!("Score:       none (selected by name)"),
+    }
+}
+
+fn dry_run(lunchable: &Lunchable, args: Vec<String>, mode: LaunchMode) -> Result<()> {
+    let cmd_lines = lunchable.command_lines(args)?;
+    println!("Launching:   {}", lunchable);
+    println!("Mode:        {:?}", mode);
+    match (mode, lunchable.log_file()) {
+        (LaunchMode::Detach, Some(path)) => println!("Output:      {}", path.display()),
+        (LaunchMode::Detach, None) => println!("Output:      discarded"),
+        _ => println!("Output:      inherited"),
+    }
+    match lunchable.work_dir() {
+        Some(work_dir) => println!("Work dir:    {}", work_dir.display()),
+        None => println!("Work dir:    unchanged"),
+    }
+    match mode {
+        LaunchMode::Detach => println!("Process:     new session, signals reset"),
+        LaunchMode::Wait => println!("Process:     child, signals reset, SIGINT/SIGTERM forwarded"),
+        LaunchMode::Exec if cmd_lines.len() > 1 => {
+            println!("Process:     last command replaces lunch, others detached, signals reset")
+        }
+        LaunchMode::Exec => println!("Process:     replaces lunch, signals reset"),
+    }
+    for cmd_line in cmd_lines {
+        let argv: Vec<_> = Some(&cmd_line.cmd)
+            .into_iter()
+            .chain(cmd_line.args.iter())
+            .collect();
+        println!("Command:     {:?}", argv);
+    }
+    Ok(())
+}
+
+fn launch(lunchable: &Lunchable, args: Vec<String>, mode: LaunchMode) -> Result<()> {
+    info!("Launching '{}'...", lunchable);
+    let outcome = lunchable.launch(args, mode)?;
+    debug!("Launched '{}': {:?}", lunchable, outcome);
+    if let LaunchOutcome::Exec(_, cmd) = outcome {
+        return Err(cmd.exec());
+    }
+    match outcome.exit_code() {
+        0 => Ok(()),
+        code => ::std::process::exit(code),
     }
 }
@@ -0,0 +1,39 @@
+extern crate lunch;
+extern crate rayon;
+#[macro_use]
+extern crate spectral;
+
+use spectral::prelude::*;
+
+use lunch::errors::*;
+use lunch::{CmdLine, Launch, LaunchMode, LaunchOutcome};
+
+// Signals sent to the test process reach every waited child, so this runs in its own binary
+
+struct SignalSelf;
+
+impl Launch for SignalSelf {
+    fn command_lines(&self, _args: Vec<String>) -> Result<Vec<CmdLine>> {
+        Ok(vec![
+            CmdLine {
+                cmd: "sh".to_owned(),
+                args: vec![
+                    "-c".to_owned(),
+                    "trap 'exit 7' TERM; kill -TERM $PPID; sleep 5 & wait".to_owned(),
+                ],
+            },
+        ])
+    }
+}
+
+#[test]
+fn wait_forwards_signals_with_thread_pool() {
+    // worker threads that leave the signals unblocked, as desktop file loading starts them
+    rayon::join(|| (), || ());
+    match SignalSelf.launch(vec![], LaunchMode::Wait) {
+        Ok(LaunchOutcome::Exited(statuses)) => {
+            assert_that!(statuses[0].code()).is_equal_to(Some(7))
+        }
+        outcome => panic!("unexpected outcome {:?}", outcome),
+    }
+}